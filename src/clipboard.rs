@@ -0,0 +1,39 @@
+use std::io::Write;
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use color_eyre::Result;
+
+use crate::screen;
+
+/// The system clipboard, or the terminal's through an OSC 52 sequence when there is none, as
+/// over SSH where the local clipboard is only reachable through the terminal.
+pub struct Clipboard {
+    // kept for the whole session, X11 selections being served by the process that set them
+    system: Option<arboard::Clipboard>,
+}
+
+impl Clipboard {
+    pub fn new() -> Self {
+        let remote = std::env::var_os("SSH_TTY").is_some();
+        Self {
+            system: if remote {
+                None
+            } else {
+                arboard::Clipboard::new().ok()
+            },
+        }
+    }
+
+    pub fn copy(&mut self, text: &str) -> Result<()> {
+        if let Some(system) = &mut self.system
+            && system.set_text(text).is_ok()
+        {
+            return Ok(());
+        }
+
+        let mut terminal = screen::output()?;
+        write!(terminal, "\x1b]52;c;{}\x07", STANDARD.encode(text))?;
+        terminal.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,155 @@
+use std::{
+    fs::{self, File},
+    io::{self, Write},
+    path::Path,
+    str::FromStr,
+};
+
+use json_tui::{DefaultKey, Tree, node::Indent};
+
+/// A command typed after `:`.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Command {
+    /// `w <file>` writes the document, `ws <file>` the selected node, as pretty-printed JSON. An
+    /// existing file is only replaced with `w!` or `ws!`.
+    Write {
+        path: String,
+        selection: bool,
+        force: bool,
+    },
+    /// `w` alone writes the document back to the file it was read from.
+    Save,
+}
+
+impl FromStr for Command {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let input = input.trim();
+        let (name, argument) = match input.split_once(char::is_whitespace) {
+            Some((name, argument)) => (name, argument.trim()),
+            None => (input, ""),
+        };
+
+        let (selection, force) = match name {
+            "w" => (false, false),
+            "w!" => (false, true),
+            "ws" => (true, false),
+            "ws!" => (true, true),
+            _ => return Err(format!("unknown command \"{name}\"")),
+        };
+
+        if argument.is_empty() && !selection {
+            return Ok(Command::Save);
+        }
+        if argument.is_empty() {
+            return Err(format!("{name} expects a file name"));
+        }
+
+        Ok(Command::Write {
+            path: argument.to_string(),
+            selection,
+            force,
+        })
+    }
+}
+
+/// Writes `json` to `path`, refusing to replace an existing file unless `force` is set, and
+/// returns the number of bytes written.
+pub fn write_json(path: &Path, json: &str, force: bool) -> io::Result<usize> {
+    let mut file = if force {
+        File::create(path)?
+    } else {
+        File::create_new(path).map_err(|e| match e.kind() {
+            io::ErrorKind::AlreadyExists => {
+                io::Error::new(e.kind(), "file exists, add ! to overwrite it")
+            }
+            _ => e,
+        })?
+    };
+    file.write_all(json.as_bytes())?;
+
+    Ok(json.len())
+}
+
+/// Replaces the file at `path` with `json`, and returns the number of bytes written. The JSON
+/// goes to a file next to it first, renamed over it once complete, so that a failure midway
+/// leaves the file as it was. A symlink is followed to the file it points at, which is replaced
+/// in its place.
+pub fn save_json(path: &Path, json: &str) -> io::Result<usize> {
+    let path = &fs::canonicalize(path)?;
+    let name = path.file_name().ok_or(io::ErrorKind::InvalidInput)?;
+
+    // named after the process, and counted past files left by one that was cut short
+    let (temporary, mut file) = (0..)
+        .map(|attempt| {
+            let mut temporary = name.to_owned();
+            temporary.push(format!(".json_tui.{}.{attempt}.tmp", std::process::id()));
+            let temporary = path.with_file_name(temporary);
+            File::create_new(&temporary).map(|file| (temporary, file))
+        })
+        .find(|created| {
+            !created
+                .as_ref()
+                .is_err_and(|e| e.kind() == io::ErrorKind::AlreadyExists)
+        })
+        .unwrap()?;
+
+    let written = (|| {
+        file.write_all(json.as_bytes())?;
+        file.sync_all()?;
+        fs::set_permissions(&temporary, fs::metadata(path)?.permissions())?;
+        fs::rename(&temporary, path)
+    })();
+    if written.is_err() {
+        let _ = fs::remove_file(&temporary);
+    }
+    written?;
+
+    Ok(json.len())
+}
+
+/// Pretty-prints the node `key` of `tree` indented by `indent`, with a final line break.
+pub fn to_json(tree: &Tree, key: DefaultKey, indent: Indent) -> String {
+    let mut json = tree.to_json(key, indent);
+    json.push('\n');
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saving_steps_past_a_stale_temporary_file() {
+        let dir = std::env::temp_dir().join(format!("json_tui_save_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("doc.json");
+        fs::write(&path, "[1]\n").unwrap();
+        // as left by a save of this process that was cut short
+        let stale = dir.join(format!("doc.json.json_tui.{}.0.tmp", std::process::id()));
+        fs::write(&stale, "[").unwrap();
+
+        assert_eq!(save_json(&path, "[2]\n").unwrap(), 4);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "[2]\n");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn saving_through_a_symlink_replaces_the_file_it_points_at() {
+        let dir = std::env::temp_dir().join(format!("json_tui_link_{}", std::process::id()));
+        fs::create_dir_all(dir.join("real")).unwrap();
+        let target = dir.join("real").join("doc.json");
+        fs::write(&target, "[1]\n").unwrap();
+        let link = dir.join("doc.json");
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        save_json(&link, "[2]\n").unwrap();
+        assert!(fs::symlink_metadata(&link).unwrap().is_symlink());
+        assert_eq!(fs::read_to_string(&target).unwrap(), "[2]\n");
+        assert_eq!(fs::read_dir(dir.join("real")).unwrap().count(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
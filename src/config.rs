@@ -0,0 +1,207 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use color_eyre::{Result, eyre::eyre};
+use ratatui::style::Color;
+use serde::Deserialize;
+
+use json_tui::{node::QuoteStyle, path::PathStyle, theme::Theme};
+
+use crate::keymap::{Action, Keymap, Keys};
+
+/// Contents of `$XDG_CONFIG_HOME/json_tui/config.toml`, every field being optional.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    theme: Option<String>,
+    selection: Option<SelectionStyle>,
+    collapsed: Option<CollapsedStyle>,
+    quotes: Option<QuoteStyle>,
+    string_limit: Option<usize>,
+    path_style: Option<PathStyle>,
+    undo_depth: Option<usize>,
+    collapse_arrays: Option<usize>,
+    max_size: Option<u64>,
+    #[serde(default)]
+    select_expanded: bool,
+    #[serde(default)]
+    focus_mode: bool,
+    #[serde(default)]
+    colors: ColorsConfig,
+    /// Keys replacing the defaults of the actions they are set for.
+    #[serde(default)]
+    keys: BTreeMap<Action, Keys>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum SelectionStyle {
+    Background,
+    Reverse,
+}
+
+/// How collapsed containers are summarised.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum CollapsedStyle {
+    Count,
+    Preview,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ColorsConfig {
+    key: Option<String>,
+    string: Option<String>,
+    number: Option<String>,
+    float: Option<String>,
+    boolean: Option<String>,
+    null: Option<String>,
+    punctuation: Option<String>,
+    selection_fg: Option<String>,
+    selection_bg: Option<String>,
+    match_bg: Option<String>,
+    added: Option<String>,
+    removed: Option<String>,
+    changed: Option<String>,
+}
+
+impl ColorsConfig {
+    fn apply(&self, theme: &mut Theme, file: &Path) -> Result<()> {
+        let fields = [
+            ("key", &self.key, &mut theme.key),
+            ("string", &self.string, &mut theme.string),
+            ("number", &self.number, &mut theme.number),
+            ("float", &self.float, &mut theme.float),
+            ("boolean", &self.boolean, &mut theme.boolean),
+            ("null", &self.null, &mut theme.null),
+            ("punctuation", &self.punctuation, &mut theme.punctuation),
+            ("selection_bg", &self.selection_bg, &mut theme.selection_bg),
+            ("match_bg", &self.match_bg, &mut theme.match_bg),
+            ("added", &self.added, &mut theme.added),
+            ("removed", &self.removed, &mut theme.removed),
+            ("changed", &self.changed, &mut theme.changed),
+        ];
+
+        for (field, value, color) in fields {
+            if let Some(value) = value {
+                *color = parse_color(field, value, file)?;
+            }
+        }
+
+        if let Some(value) = &self.selection_fg {
+            theme.selection_fg = Some(parse_color("selection_fg", value, file)?);
+        }
+
+        Ok(())
+    }
+}
+
+fn parse_color(field: &str, value: &str, file: &Path) -> Result<Color> {
+    Color::from_str(value).map_err(|_| {
+        eyre!(
+            "invalid colour \"{value}\" for `colors.{field}` in {}, expected a colour name or #rrggbb",
+            file.display()
+        )
+    })
+}
+
+fn find_preset(name: &str) -> Result<Theme> {
+    Theme::preset(name).ok_or_else(|| {
+        eyre!(
+            "unknown theme \"{name}\", expected one of: {}",
+            Theme::PRESETS.join(", ")
+        )
+    })
+}
+
+pub fn config_file() -> Option<PathBuf> {
+    let config_dir = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => Path::new(&std::env::var_os("HOME")?).join(".config"),
+    };
+
+    Some(config_dir.join("json_tui").join("config.toml"))
+}
+
+fn read_config(file: &Path) -> Result<ConfigFile> {
+    match std::fs::read_to_string(file) {
+        Ok(data) => toml::from_str(&data).map_err(|e| eyre!("{}: {e}", file.display())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(ConfigFile::default()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Settings read from the config file, with the defaults for anything it leaves out.
+#[derive(Debug)]
+pub struct Config {
+    pub theme: Theme,
+    pub collapsed_preview: bool,
+    pub quotes: QuoteStyle,
+    pub string_limit: Option<usize>,
+    /// The style paths are copied in first.
+    pub path_style: PathStyle,
+    /// Edits kept to be undone.
+    pub undo_depth: Option<usize>,
+    /// Arrays of scalars longer than this start collapsed, see `--collapse-arrays`.
+    pub collapse_arrays: Option<usize>,
+    /// Inputs of more megabytes are only parsed once confirmed, see `--max-size`.
+    pub max_size: Option<u64>,
+    /// Whether expanding a container selects its first child.
+    pub select_expanded: bool,
+    /// Whether moving out of a container collapses it.
+    pub focus_mode: bool,
+    pub keymap: Keymap,
+    /// Problems worth reporting that do not prevent starting, such as conflicting keys.
+    pub warnings: Vec<String>,
+}
+
+/// Builds the theme from the preset named on the command line or in the config file,
+/// then overrides it with any colour set in the config file, and the keymap from the keys it
+/// remaps.
+pub fn load(preset: Option<&str>) -> Result<Config> {
+    let Some(file) = config_file() else {
+        return Ok(Config {
+            theme: find_preset(preset.unwrap_or("dark"))?,
+            collapsed_preview: false,
+            quotes: QuoteStyle::default(),
+            string_limit: None,
+            path_style: PathStyle::default(),
+            undo_depth: None,
+            collapse_arrays: None,
+            max_size: None,
+            select_expanded: false,
+            focus_mode: false,
+            keymap: Keymap::default(),
+            warnings: vec![],
+        });
+    };
+
+    let config = read_config(&file)?;
+    let mut theme = find_preset(preset.or(config.theme.as_deref()).unwrap_or("dark"))?;
+    config.colors.apply(&mut theme, &file)?;
+
+    if let Some(selection) = config.selection {
+        theme.selection_reverse = matches!(selection, SelectionStyle::Reverse);
+    }
+
+    let (keymap, warnings) = Keymap::new(&config.keys);
+
+    Ok(Config {
+        theme,
+        collapsed_preview: matches!(config.collapsed, Some(CollapsedStyle::Preview)),
+        quotes: config.quotes.unwrap_or_default(),
+        string_limit: config.string_limit,
+        path_style: config.path_style.unwrap_or_default(),
+        undo_depth: config.undo_depth,
+        collapse_arrays: config.collapse_arrays,
+        max_size: config.max_size,
+        select_expanded: config.select_expanded,
+        focus_mode: config.focus_mode,
+        keymap,
+        warnings,
+    })
+}
@@ -0,0 +1,73 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::node::{NodeView, Tree};
+
+/// How a node differs from the document it is compared with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffStatus {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// Compares two documents and flags their differences: entries only found in `left` are marked
+/// removed, those only found in `right` added, and values present on both sides but differing are
+/// marked changed in both trees. Objects are matched by key and arrays by position.
+pub fn mark_differences(left: &mut Tree, right: &mut Tree) {
+    left.build_all();
+    right.build_all();
+
+    let mut removed = vec![];
+    let mut added = vec![];
+    let mut changed = vec![];
+
+    let mut stack = vec![(left.root(), right.root())];
+
+    while let Some((l, r)) = stack.pop() {
+        match (left.view(l), right.view(r)) {
+            (NodeView::Terminal(a), NodeView::Terminal(b)) => {
+                if a != b {
+                    changed.push((l, r));
+                }
+            }
+            (NodeView::Array(a), NodeView::Array(b)) => {
+                stack.extend(a.iter().copied().zip(b.iter().copied()));
+                removed.extend(a.iter().skip(b.len()));
+                added.extend(b.iter().skip(a.len()));
+            }
+            (NodeView::Object(a), NodeView::Object(b)) => {
+                let right_entries: HashMap<&str, _> =
+                    b.iter().map(|(name, k)| (name.as_str(), *k)).collect();
+
+                for (name, l) in a {
+                    match right_entries.get(name.as_str()) {
+                        Some(r) => stack.push((*l, *r)),
+                        None => removed.push(*l),
+                    }
+                }
+
+                let left_names: HashSet<&str> = a.iter().map(|(name, _)| name.as_str()).collect();
+                added.extend(
+                    b.iter()
+                        .filter(|(name, _)| !left_names.contains(&name.as_str()))
+                        .map(|(_, k)| *k),
+                );
+            }
+            // a value replaced by one of another type
+            _ => changed.push((l, r)),
+        }
+    }
+
+    for key in removed {
+        left.mark_diff(key, DiffStatus::Removed);
+    }
+
+    for key in added {
+        right.mark_diff(key, DiffStatus::Added);
+    }
+
+    for (l, r) in changed {
+        left.mark_diff(l, DiffStatus::Changed);
+        right.mark_diff(r, DiffStatus::Changed);
+    }
+}
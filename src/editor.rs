@@ -0,0 +1,55 @@
+use std::{
+    fs::{self, File},
+    io::{self, IsTerminal, Write},
+    path::Path,
+    process::{Command, ExitStatus},
+};
+
+use crate::screen::{self, Screen};
+
+/// Lets the user change `text` in their editor, `$VISUAL` or `$EDITOR` and else `vi`, with the
+/// interface suspended meanwhile. Returns what the file holds once the editor exits.
+pub fn edit(terminal: &mut Screen, text: &str) -> io::Result<String> {
+    let path = std::env::temp_dir().join(format!("json_tui-{}.json", std::process::id()));
+    File::create_new(&path)?.write_all(text.as_bytes())?;
+
+    screen::restore()?;
+    let status = run(&path);
+    screen::resume(terminal)?;
+
+    let edited = status.and_then(|status| {
+        if status.success() {
+            fs::read_to_string(&path)
+        } else {
+            Err(io::Error::other(format!("the editor exited with {status}")))
+        }
+    });
+    let _ = fs::remove_file(&path);
+    edited
+}
+
+fn run(path: &Path) -> io::Result<ExitStatus> {
+    let editor = ["VISUAL", "EDITOR"]
+        .into_iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string());
+
+    // the editor can come with arguments, as in `code --wait`
+    let mut words = editor.split_whitespace();
+    let mut command = Command::new(words.next().expect("the editor is not blank"));
+    command.args(words).arg(path);
+
+    // the document can be read from stdin and the picked value go to stdout, the editor needs
+    // the terminal on both
+    if !io::stdin().is_terminal() {
+        command.stdin(File::open("/dev/tty")?);
+    }
+    if !io::stdout().is_terminal() {
+        command.stdout(File::options().write(true).open("/dev/tty")?);
+    }
+
+    command
+        .status()
+        .map_err(|e| io::Error::new(e.kind(), format!("could not run {editor}: {e}")))
+}
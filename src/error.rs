@@ -0,0 +1,63 @@
+use std::fmt;
+
+/// Reasons a [`Tree`](crate::Tree) cannot be built from some input.
+#[derive(Debug)]
+pub enum TreeError {
+    /// The input holds nothing but whitespace.
+    Empty,
+    /// The input is not valid JSON.
+    Parse(serde_json::Error),
+}
+
+impl fmt::Display for TreeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TreeError::Empty => write!(f, "the input is empty"),
+            TreeError::Parse(e) => write!(f, "invalid JSON: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for TreeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TreeError::Empty => None,
+            TreeError::Parse(e) => Some(e),
+        }
+    }
+}
+
+impl From<serde_json::Error> for TreeError {
+    fn from(e: serde_json::Error) -> Self {
+        TreeError::Parse(e)
+    }
+}
+
+/// Reasons an edit of a [`Tree`](crate::Tree) is refused.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EditError {
+    /// The document is a lone scalar, with no container to add to.
+    NoContainer,
+    /// Children were added to a node that is not an object or array.
+    NotAContainer,
+    /// An object entry was given without a key, or an array element with one.
+    KeyMismatch,
+    /// The object already has an entry under this key.
+    DuplicateKey(String),
+    /// Only object entries have a key to rename.
+    NotAnEntry,
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EditError::NoContainer => write!(f, "the document has no object or array to add to"),
+            EditError::NotAContainer => write!(f, "the selected node is not an object or array"),
+            EditError::KeyMismatch => write!(f, "objects take a key and a value, arrays a value"),
+            EditError::DuplicateKey(name) => write!(f, "the object already has a key \"{name}\""),
+            EditError::NotAnEntry => write!(f, "the selected node is not an object entry"),
+        }
+    }
+}
+
+impl std::error::Error for EditError {}
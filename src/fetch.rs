@@ -0,0 +1,42 @@
+use std::process::{Command, Stdio};
+
+use color_eyre::{Result, eyre::eyre};
+
+/// Whether an input names a URL to fetch rather than a file.
+pub fn is_url(input: &str) -> bool {
+    input.starts_with("https://") || input.starts_with("http://")
+}
+
+/// Downloads the body at `url` with curl, sending `headers` (`Name: value`) and following
+/// redirects. A failed connection, a transfer stalled for 30 seconds or a response other than
+/// 2xx fails with what curl says about it.
+pub fn fetch(url: &str, headers: &[String]) -> Result<Vec<u8>> {
+    let mut command = Command::new("curl");
+    command
+        .args(["--silent", "--show-error", "--fail", "--location"])
+        .args([
+            "--connect-timeout",
+            "10",
+            "--speed-limit",
+            "1",
+            "--speed-time",
+            "30",
+        ]);
+    for header in headers {
+        command.arg("--header").arg(header);
+    }
+
+    let output = command
+        .arg(url)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| eyre!("{url}: could not run curl, which fetches URLs: {e}"))?;
+
+    if !output.status.success() {
+        let message = String::from_utf8_lossy(&output.stderr);
+        let message = message.trim().trim_start_matches("curl: ");
+        return Err(eyre!("{url}: {message}"));
+    }
+
+    Ok(output.stdout)
+}
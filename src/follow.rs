@@ -0,0 +1,72 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, Read},
+    sync::mpsc::{self, Receiver},
+    thread,
+    time::Duration,
+};
+
+use json_tui::source;
+use serde_json::Value;
+
+// how often a file that reached its end is checked for new lines
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// A line of the followed input, with its value unless it is not valid JSON.
+pub struct Line {
+    pub text: String,
+    pub value: Option<Value>,
+}
+
+/// Reads the lines of `path`, or of stdin for `None`, from the start on another thread, parsing
+/// each as it is complete. A file is read again as it grows, as `tail -f` does, while stdin is
+/// read until it is closed. Blank lines are left out.
+pub fn follow(path: Option<&str>) -> io::Result<Receiver<Line>> {
+    let (input, growing): (Box<dyn Read + Send>, bool) = match path {
+        Some(path) => {
+            let file =
+                File::open(path).map_err(|e| io::Error::new(e.kind(), format!("{path}: {e}")))?;
+            (Box::new(file), true)
+        }
+        None => (Box::new(io::stdin()), false),
+    };
+    let (sender, receiver) = mpsc::channel();
+
+    thread::spawn(move || {
+        let mut input = BufReader::new(input);
+        // a line being written, read up to the end of the file so far
+        let mut pending = vec![];
+
+        loop {
+            let read = match input.read_until(b'\n', &mut pending) {
+                Ok(read) => read,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(_) => return,
+            };
+            if read == 0 && growing {
+                thread::sleep(POLL_INTERVAL);
+                continue;
+            }
+            if read == 0 && pending.is_empty() {
+                return;
+            }
+            // the rest of the line is still to come, unless stdin was closed
+            if read > 0 && !pending.ends_with(b"\n") {
+                continue;
+            }
+
+            let text = String::from_utf8_lossy(&pending).trim_end().to_string();
+            pending.clear();
+            if text.trim().is_empty() {
+                continue;
+            }
+            let value = source::read_value(&text).ok();
+            if sender.send(Line { text, value }).is_err() {
+                // the interface quit
+                return;
+            }
+        }
+    });
+
+    Ok(receiver)
+}
@@ -0,0 +1,117 @@
+use std::collections::VecDeque;
+
+use serde_json::Value;
+
+use crate::path::PathSegment;
+
+/// An edit of a [`crate::Tree`], applying one giving back the edit that undoes it. Nodes are
+/// found by path, their keys not surviving being deleted and added back.
+#[derive(Debug, Clone)]
+pub(crate) enum Change {
+    /// Replaces the node at `path`, with its descendants, with `value`.
+    Replace {
+        path: Vec<PathSegment>,
+        value: Value,
+    },
+    /// Adds `value` at `index` of the container at `parent`, under `name` in an object.
+    Insert {
+        parent: Vec<PathSegment>,
+        index: usize,
+        name: Option<String>,
+        value: Value,
+    },
+    /// Removes the child at `index` of the container at `parent`.
+    Remove {
+        parent: Vec<PathSegment>,
+        index: usize,
+    },
+    /// Renames the entry at `index` of the object at `parent`.
+    Rename {
+        parent: Vec<PathSegment>,
+        index: usize,
+        name: String,
+    },
+}
+
+/// The edits undoing the last changes of a tree, and those redoing what was undone.
+#[derive(Debug)]
+pub(crate) struct History {
+    undo: VecDeque<Change>,
+    redo: Vec<Change>,
+    /// Changes kept to be undone, the oldest being dropped past it since deleted subtrees are
+    /// kept whole.
+    depth: usize,
+    /// Edits made since the tree was loaded, less those undone.
+    position: usize,
+    /// The position the tree was last saved at, `None` once no undoing or redoing leads back to
+    /// what was saved.
+    saved: Option<usize>,
+}
+
+impl History {
+    pub const DEPTH: usize = 100;
+
+    pub fn new() -> Self {
+        Self {
+            undo: VecDeque::new(),
+            redo: vec![],
+            depth: Self::DEPTH,
+            position: 0,
+            saved: Some(0),
+        }
+    }
+
+    /// Keeps `inverse` to undo a new edit, which leaves nothing to redo.
+    pub fn record(&mut self, inverse: Change) {
+        self.redo.clear();
+        // a saved state that was undone past can no longer be redone
+        if self.saved.is_some_and(|saved| saved > self.position) {
+            self.saved = None;
+        }
+        self.push_undo(inverse);
+    }
+
+    pub fn push_undo(&mut self, change: Change) {
+        self.undo.push_back(change);
+        self.position += 1;
+        self.trim();
+    }
+
+    pub fn pop_undo(&mut self) -> Option<Change> {
+        let change = self.undo.pop_back()?;
+        self.position -= 1;
+        Some(change)
+    }
+
+    pub fn push_redo(&mut self, change: Change) {
+        self.redo.push(change);
+    }
+
+    pub fn pop_redo(&mut self) -> Option<Change> {
+        self.redo.pop()
+    }
+
+    /// Notes that the tree was saved as it is now.
+    pub fn mark_saved(&mut self) {
+        self.saved = Some(self.position);
+    }
+
+    /// Whether the tree is as it was last saved, or loaded, having had as many edits undone as
+    /// redone since.
+    pub fn is_saved(&self) -> bool {
+        self.saved == Some(self.position)
+    }
+
+    pub fn set_depth(&mut self, depth: usize) {
+        self.depth = depth;
+        self.trim();
+        let excess = self.redo.len().saturating_sub(depth);
+        self.redo.drain(..excess);
+    }
+
+    /// Drops the oldest changes past the depth.
+    fn trim(&mut self) {
+        let excess = self.undo.len().saturating_sub(self.depth);
+        self.undo.drain(..excess);
+    }
+}
@@ -0,0 +1,654 @@
+use std::fmt;
+
+use color_eyre::{Result, eyre::eyre};
+use json_tui::{TreeError, source};
+use serde_json::Value;
+use unicode_width::UnicodeWidthChar;
+
+/// Characters of the failing line shown on each side of the column, minified JSON being a
+/// single long line.
+const SNIPPET_CONTEXT: usize = 40;
+
+/// Input formats that can be converted into a JSON tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    /// Newline-delimited JSON, one value per line as in logs, read as an array of the values.
+    Ndjson,
+    Yaml,
+    Toml,
+}
+
+impl Format {
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name {
+            "json" => Ok(Format::Json),
+            "ndjson" | "jsonl" => Ok(Format::Ndjson),
+            "yaml" | "yml" => Ok(Format::Yaml),
+            "toml" => Ok(Format::Toml),
+            _ => Err(eyre!(
+                "unknown format \"{name}\", expected one of: json, ndjson, yaml, toml"
+            )),
+        }
+    }
+
+    /// Guesses the format from the file extension, defaulting to JSON.
+    pub fn detect(path: Option<&str>) -> Self {
+        let extension = path
+            .and_then(|p| std::path::Path::new(p).extension())
+            .and_then(|e| e.to_str());
+
+        // `logs.ndjson.gz` holds NDJSON
+        let extension = match extension {
+            Some(compressed) if Compression::EXTENSIONS.contains(&compressed) => path
+                .and_then(|p| std::path::Path::new(p).file_stem())
+                .and_then(|stem| std::path::Path::new(stem).extension())
+                .and_then(|e| e.to_str()),
+            extension => extension,
+        };
+
+        match extension {
+            Some("ndjson" | "jsonl") => Format::Ndjson,
+            Some("yaml" | "yml") => Format::Yaml,
+            Some("toml") => Format::Toml,
+            _ => Format::Json,
+        }
+    }
+}
+
+/// Text encodings inputs can be read in, UTF-8 being expected unless a byte order mark or
+/// `--encoding` says otherwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Latin1,
+}
+
+impl Encoding {
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name.to_lowercase().as_str() {
+            "utf-8" | "utf8" => Ok(Encoding::Utf8),
+            "utf-16le" | "utf16le" => Ok(Encoding::Utf16Le),
+            "utf-16be" | "utf16be" => Ok(Encoding::Utf16Be),
+            "latin1" | "latin-1" | "iso-8859-1" => Ok(Encoding::Latin1),
+            _ => Err(eyre!(
+                "unknown encoding \"{name}\", expected one of: utf-8, utf-16le, utf-16be, latin1"
+            )),
+        }
+    }
+
+    /// Tells the encoding from a byte order mark, or from the zero bytes UTF-16 puts around the
+    /// ASCII characters a document starts with, returning it along with the length of the mark.
+    fn detect(bytes: &[u8]) -> (Self, usize) {
+        match bytes {
+            [0xEF, 0xBB, 0xBF, ..] => (Encoding::Utf8, 3),
+            [0xFF, 0xFE, ..] => (Encoding::Utf16Le, 2),
+            [0xFE, 0xFF, ..] => (Encoding::Utf16Be, 2),
+            [a, 0, b, 0, ..] if *a != 0 && *b != 0 => (Encoding::Utf16Le, 0),
+            [0, a, 0, b, ..] if *a != 0 && *b != 0 => (Encoding::Utf16Be, 0),
+            _ => (Encoding::Utf8, 0),
+        }
+    }
+}
+
+/// Converts the bytes of input `name` to text, in `encoding` or the one detected. UTF-8 input is
+/// taken over without copying. Invalid sequences fail unless `lossy`, being replaced with `�`
+/// then, which the returned warning tells.
+pub fn decode(
+    name: &str,
+    bytes: Vec<u8>,
+    encoding: Option<Encoding>,
+    lossy: bool,
+) -> Result<(String, Option<String>)> {
+    let (detected, bom) = Encoding::detect(&bytes);
+    let bom = match encoding {
+        Some(encoding) if encoding != detected => 0,
+        _ => bom,
+    };
+
+    match encoding.unwrap_or(detected) {
+        Encoding::Utf8 => {
+            let mut bytes = bytes;
+            bytes.drain(..bom);
+            match String::from_utf8(bytes) {
+                Ok(text) => Ok((text, None)),
+                Err(e) if lossy => {
+                    let offset = bom + e.utf8_error().valid_up_to();
+                    let bytes = e.into_bytes();
+                    let invalid = bytes
+                        .utf8_chunks()
+                        .filter(|chunk| !chunk.invalid().is_empty())
+                        .count();
+                    let text = String::from_utf8_lossy(&bytes).into_owned();
+                    Ok((text, Some(replaced(name, invalid, offset))))
+                }
+                Err(e) => Err(eyre!(
+                    "{name} is not valid UTF-8 (invalid byte at offset {}), \
+                     --encoding can be given to read it as UTF-16 or Latin-1, or --lossy to \
+                     replace invalid bytes",
+                    bom + e.utf8_error().valid_up_to()
+                )),
+            }
+        }
+        Encoding::Latin1 => Ok((bytes.iter().map(|&b| char::from(b)).collect(), None)),
+        encoding @ (Encoding::Utf16Le | Encoding::Utf16Be) => {
+            let bytes = &bytes[bom..];
+            if !bytes.len().is_multiple_of(2) {
+                return Err(eyre!("{name} is not valid UTF-16 (odd number of bytes)"));
+            }
+            let units = bytes.chunks_exact(2).map(|pair| {
+                let pair = [pair[0], pair[1]];
+                match encoding {
+                    Encoding::Utf16Le => u16::from_le_bytes(pair),
+                    _ => u16::from_be_bytes(pair),
+                }
+            });
+            let mut text = String::with_capacity(bytes.len() / 2);
+            // code units read, and the number and first offset of those replaced
+            let mut position = 0;
+            let mut invalid = 0;
+            let mut first = None;
+            for c in char::decode_utf16(units) {
+                match c {
+                    Ok(c) => {
+                        position += c.len_utf16();
+                        text.push(c);
+                    }
+                    Err(_) if lossy => {
+                        first.get_or_insert(bom + 2 * position);
+                        position += 1;
+                        invalid += 1;
+                        text.push(char::REPLACEMENT_CHARACTER);
+                    }
+                    Err(_) => {
+                        return Err(eyre!(
+                            "{name} is not valid UTF-16 (unpaired surrogate at offset {}), \
+                             --lossy can be given to replace it",
+                            bom + 2 * position
+                        ));
+                    }
+                }
+            }
+            Ok((text, first.map(|offset| replaced(name, invalid, offset))))
+        }
+    }
+}
+
+/// Warns that `invalid` sequences of input `name` were replaced, the first at byte `offset`.
+fn replaced(name: &str, invalid: usize, offset: usize) -> String {
+    let sequences = if invalid == 1 {
+        "sequence"
+    } else {
+        "sequences"
+    };
+    format!("{name}: replaced {invalid} invalid {sequences} with �, the first at byte {offset}")
+}
+
+/// Compressed inputs, told apart by the magic bytes they start with whatever their name, so
+/// that piped archives are decompressed too.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    /// Extensions of compressed files, looked past to guess the format.
+    const EXTENSIONS: [&str; 3] = ["gz", "zst", "zstd"];
+
+    pub fn detect(bytes: &[u8]) -> Option<Self> {
+        match bytes {
+            [0x1F, 0x8B, ..] => Some(Compression::Gzip),
+            [0x28, 0xB5, 0x2F, 0xFD, ..] => Some(Compression::Zstd),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Compression::Gzip => "gzip",
+            Compression::Zstd => "zstd",
+        })
+    }
+}
+
+/// Decompresses input `name`, the compressed bytes being freed before the decompressed ones
+/// are returned.
+#[cfg(feature = "compression")]
+pub fn decompress(name: &str, compression: Compression, bytes: Vec<u8>) -> Result<Vec<u8>> {
+    use std::io::Read;
+
+    let corrupted =
+        |e: &dyn fmt::Display| eyre!("{name} is a corrupted {compression} archive: {e}");
+    let mut data = vec![];
+
+    match compression {
+        Compression::Gzip => {
+            // concatenated archives, as from `cat a.gz b.gz`, hold one member each
+            flate2::read::MultiGzDecoder::new(bytes.as_slice())
+                .read_to_end(&mut data)
+                .map_err(|e| corrupted(&e))?;
+        }
+        Compression::Zstd => {
+            let mut rest = bytes.as_slice();
+            while !rest.is_empty() {
+                let mut frame = ruzstd::decoding::StreamingDecoder::new(&mut rest)
+                    .map_err(|e| corrupted(&e))?;
+                frame.read_to_end(&mut data).map_err(|e| corrupted(&e))?;
+                let decoder = &frame.decoder;
+                if let (Some(expected), Some(found)) = (
+                    decoder.get_checksum_from_data(),
+                    decoder.get_calculated_checksum(),
+                ) && expected != found
+                {
+                    return Err(corrupted(&"checksum mismatch"));
+                }
+            }
+        }
+    }
+
+    drop(bytes);
+    Ok(data)
+}
+
+#[cfg(not(feature = "compression"))]
+pub fn decompress(name: &str, compression: Compression, _: Vec<u8>) -> Result<Vec<u8>> {
+    Err(eyre!(
+        "{name} is {compression}-compressed, which requires json_tui to be built with the \
+         `compression` feature"
+    ))
+}
+
+/// A parsed input, with the number of NDJSON lines left out for being invalid and what was left
+/// out after the documents of JSON input.
+pub struct Parsed {
+    pub value: Value,
+    pub skipped: usize,
+    pub trailing: Option<Trailing>,
+}
+
+/// Where JSON input stops being readable after its documents, as with a comment or junk after
+/// the closing bracket.
+pub struct Trailing {
+    /// In bytes into the input, or into the strict JSON `--lenient` turns it into.
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+    /// Whether it starts with a comment, which `--lenient` reads.
+    pub comment: bool,
+}
+
+impl Trailing {
+    fn at(text: &str, offset: usize) -> Self {
+        let before = &text[..offset];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        let rest = &text[offset..];
+        Trailing {
+            offset,
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+            comment: rest.starts_with("//") || rest.starts_with("/*"),
+        }
+    }
+}
+
+impl fmt::Display for Trailing {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "left out what follows the JSON from line {}, column {}",
+            self.line, self.column
+        )?;
+        if self.comment {
+            write!(f, " (--lenient reads comments)")?;
+        }
+        Ok(())
+    }
+}
+
+/// How forgiving parsing is of input that is not quite valid.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Leniency {
+    /// Leaves out invalid NDJSON lines rather than failing.
+    pub skip_invalid: bool,
+    /// Accepts JSON with comments, trailing commas and unquoted keys, as in editor settings.
+    pub lenient: bool,
+}
+
+/// Input that could not be parsed, reported with the line it failed on when known.
+#[derive(Debug)]
+pub struct InvalidInput {
+    name: String,
+    message: String,
+    location: Option<Location>,
+    hint: Option<&'static str>,
+}
+
+/// Where parsing failed, the column counting bytes as `serde_json` does.
+#[derive(Debug)]
+struct Location {
+    line: usize,
+    column: usize,
+    text: String,
+}
+
+impl InvalidInput {
+    fn new(name: &str, message: String) -> Self {
+        Self {
+            name: name.to_string(),
+            message,
+            location: None,
+            hint: None,
+        }
+    }
+
+    /// Reports JSON failing in `content`, which holds the line the error points at.
+    fn json(name: &str, content: &str, error: TreeError) -> Self {
+        let TreeError::Parse(e) = error else {
+            return Self::new(name, error.to_string());
+        };
+        let text = content
+            .lines()
+            .nth(e.line().saturating_sub(1))
+            .unwrap_or("");
+
+        Self {
+            location: Some(Location {
+                line: e.line(),
+                column: e.column(),
+                text: text.to_string(),
+            }),
+            ..Self::new(name, format!("invalid JSON: {}", json_message(&e)))
+        }
+    }
+
+    fn with_hint(self, hint: Option<&'static str>) -> Self {
+        Self { hint, ..self }
+    }
+}
+
+impl fmt::Display for InvalidInput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some(at) = &self.location else {
+            write!(f, "{}: {}", self.name, self.message)?;
+            return self.write_hint(f);
+        };
+
+        writeln!(
+            f,
+            "{}:{}:{}: {}",
+            self.name, at.line, at.column, self.message
+        )?;
+        let gutter = at.line.to_string();
+        let blank = " ".repeat(gutter.len());
+        let (shown, padding) = at.snippet();
+        writeln!(f, "{blank} |")?;
+        writeln!(f, "{gutter} | {shown}")?;
+        write!(f, "{blank} | {padding}^")?;
+        self.write_hint(f)
+    }
+}
+
+impl InvalidInput {
+    fn write_hint(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.hint {
+            Some(hint) => write!(f, "\nhint: {hint}"),
+            None => Ok(()),
+        }
+    }
+}
+
+impl std::error::Error for InvalidInput {}
+
+impl Location {
+    /// The line around the column, and the blanks putting a caret under it.
+    fn snippet(&self) -> (String, String) {
+        let mut at = self.column.saturating_sub(1).min(self.text.len());
+        while !self.text.is_char_boundary(at) {
+            at -= 1;
+        }
+        let before: Vec<char> = self.text[..at].chars().collect();
+        let mut after = self.text[at..].chars();
+
+        let skipped = before.len().saturating_sub(SNIPPET_CONTEXT);
+        let mut shown: String = if skipped > 0 { "…" } else { "" }.to_string();
+        shown.extend(&before[skipped..]);
+        // tabs are kept for the caret to line up however wide the terminal draws them
+        let padding = shown
+            .chars()
+            .flat_map(|c| match c {
+                '\t' => vec!['\t'],
+                c => vec![' '; c.width().unwrap_or(0)],
+            })
+            .collect();
+
+        shown.extend(after.by_ref().take(SNIPPET_CONTEXT));
+        if after.next().is_some() {
+            shown.push('…');
+        }
+
+        (shown, padding)
+    }
+}
+
+/// The message of a `serde_json` error, without the location it ends with.
+fn json_message(e: &serde_json::Error) -> String {
+    let message = e.to_string();
+    let location = format!(" at line {} column {}", e.line(), e.column());
+    match message.strip_suffix(&location) {
+        Some(message) => message.to_string(),
+        None => message,
+    }
+}
+
+/// Parses `content`, read from input `name`, as `format`. Invalid input fails with an
+/// [`InvalidInput`].
+pub fn parse(name: &str, format: Format, content: &str, leniency: Leniency) -> Result<Parsed> {
+    let (value, trailing) = match format {
+        Format::Json if leniency.lenient => {
+            // the converted text is what the error points into, with the same lines
+            let strict = strict_json(content);
+            parse_json(&strict).map_err(|e| InvalidInput::json(name, &strict, e))?
+        }
+        Format::Json => parse_json(content).map_err(|e| {
+            let hint = lenient_hint(content, &e);
+            InvalidInput::json(name, content, e).with_hint(hint)
+        })?,
+        Format::Ndjson => return parse_ndjson(name, content, leniency.skip_invalid),
+        Format::Yaml => (parse_yaml(name, content)?, None),
+        Format::Toml => (parse_toml(name, content)?, None),
+    };
+
+    Ok(Parsed {
+        value,
+        skipped: 0,
+        trailing,
+    })
+}
+
+/// Parses JSON holding one value, or several back to back as streamed by some tools, which are
+/// read as an array of them. NDJSON is read that way as well. Anything unreadable after them,
+/// such as a comment, is left out.
+fn parse_json(content: &str) -> Result<(Value, Option<Trailing>), TreeError> {
+    let source::Values {
+        mut values,
+        trailing,
+    } = source::read_values(content)?;
+    let value = match values.len() {
+        1 => values.pop().expect("one value was read"),
+        _ => Value::Array(values),
+    };
+    Ok((value, trailing.map(|offset| Trailing::at(content, offset))))
+}
+
+/// Points at `--lenient` when JSON fails on a comment, a trailing comma or an unquoted key.
+fn lenient_hint(content: &str, error: &TreeError) -> Option<&'static str> {
+    let TreeError::Parse(e) = error else {
+        return None;
+    };
+    let line = content.lines().nth(e.line().checked_sub(1)?)?;
+    let at = line.get(e.column().saturating_sub(1)..)?;
+
+    let message = e.to_string();
+    let lenient = message.starts_with("trailing comma")
+        || message.starts_with("key must be a string")
+        || at.starts_with("//")
+        || at.starts_with("/*");
+    lenient.then_some("--lenient accepts comments, trailing commas and unquoted keys")
+}
+
+/// Turns JSON with comments, trailing commas and unquoted keys into strict JSON. Comments and
+/// trailing commas are blanked out rather than removed, for errors to point at the right line.
+fn strict_json(content: &str) -> String {
+    let is_identifier = |c: char| c.is_alphanumeric() || c == '_' || c == '$';
+    let mut strict = String::with_capacity(content.len());
+    // where the last comma went, blanked out if a closing bracket comes next
+    let mut comma = None;
+    let mut rest = content;
+
+    while let Some(c) = rest.chars().next() {
+        let taken = if rest.starts_with("//") {
+            let end = rest.find('\n').unwrap_or(rest.len());
+            strict.extend(rest[..end].chars().map(|_| ' '));
+            end
+        } else if let Some(comment) = rest.strip_prefix("/*") {
+            let end = comment.find("*/").map_or(rest.len(), |i| i + 4);
+            strict.extend(rest[..end].chars().map(|c| if c == '\n' { c } else { ' ' }));
+            end
+        } else if c == '"' {
+            // up to the closing quote, stepping over escaped characters
+            let mut escaped = false;
+            let end = rest[1..]
+                .find(|c| {
+                    let closing = c == '"' && !escaped;
+                    escaped = c == '\\' && !escaped;
+                    closing
+                })
+                .map_or(rest.len(), |i| i + 2);
+            strict.push_str(&rest[..end]);
+            comma = None;
+            end
+        } else if c.is_alphabetic() || c == '_' || c == '$' {
+            let end = rest.find(|c| !is_identifier(c)).unwrap_or(rest.len());
+            if rest[end..].trim_start().starts_with(':') {
+                strict.push_str(&format!("\"{}\"", &rest[..end]));
+            } else {
+                strict.push_str(&rest[..end]);
+            }
+            comma = None;
+            end
+        } else {
+            match c {
+                ',' => comma = Some(strict.len()),
+                '}' | ']' => {
+                    if let Some(at) = comma.take() {
+                        strict.replace_range(at..at + 1, " ");
+                    }
+                }
+                c if c.is_whitespace() => (),
+                _ => comma = None,
+            }
+            strict.push(c);
+            c.len_utf8()
+        };
+
+        rest = &rest[taken..];
+    }
+
+    strict
+}
+
+/// Parses each nonblank line as a value, in place so that big logs are not copied again.
+fn parse_ndjson(name: &str, content: &str, skip_invalid: bool) -> Result<Parsed> {
+    let mut values = vec![];
+    let mut skipped = 0;
+
+    for (i, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        match source::read_value(line) {
+            Ok(value) => values.push(value),
+            Err(_) if skip_invalid => skipped += 1,
+            Err(e) => {
+                // the error locates itself in the line alone
+                let invalid = InvalidInput {
+                    location: Some(Location {
+                        line: i + 1,
+                        column: e.column(),
+                        text: line.to_string(),
+                    }),
+                    ..InvalidInput::new(name, format!("invalid JSON: {}", json_message(&e)))
+                };
+                return Err(invalid
+                    .with_hint(Some("--skip-invalid leaves such lines out"))
+                    .into());
+            }
+        }
+    }
+
+    Ok(Parsed {
+        value: Value::Array(values),
+        skipped,
+        trailing: None,
+    })
+}
+
+#[cfg(feature = "format-yaml")]
+fn parse_yaml(name: &str, content: &str) -> Result<Value> {
+    serde_yaml::from_str(content)
+        .map_err(|e| InvalidInput::new(name, format!("invalid YAML: {e}")).into())
+}
+
+#[cfg(not(feature = "format-yaml"))]
+fn parse_yaml(_: &str, _: &str) -> Result<Value> {
+    Err(eyre!(
+        "YAML input requires json_tui to be built with the `format-yaml` feature"
+    ))
+}
+
+#[cfg(feature = "format-toml")]
+fn parse_toml(name: &str, content: &str) -> Result<Value> {
+    toml::from_str(content)
+        .map_err(|e| InvalidInput::new(name, format!("invalid TOML: {e}")).into())
+}
+
+#[cfg(not(feature = "format-toml"))]
+fn parse_toml(_: &str, _: &str) -> Result<Value> {
+    Err(eyre!(
+        "TOML input requires json_tui to be built with the `format-toml` feature"
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use json_tui::Tree;
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn stray_byte_is_replaced_only_when_lossy() {
+        // offsets count the byte order mark, which is skipped
+        let bytes = b"\xEF\xBB\xBF{\"a\": \"x\xFFy\"}".to_vec();
+
+        let error = decode("input", bytes.clone(), None, false).unwrap_err();
+        assert!(
+            error.to_string().contains("invalid byte at offset 11"),
+            "{error}"
+        );
+
+        let (text, warning) = decode("input", bytes, None, true).unwrap();
+        assert_eq!(
+            warning.as_deref(),
+            Some("input: replaced 1 invalid sequence with �, the first at byte 11")
+        );
+        let tree = Tree::try_from_str(&text).unwrap();
+        assert_eq!(tree.to_value(tree.root()), json!({"a": "x\u{FFFD}y"}));
+    }
+}
@@ -0,0 +1,680 @@
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::{Deserialize, Deserializer, de};
+
+/// What a key does when the tree has focus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Action {
+    MoveUp,
+    MoveDown,
+    Center,
+    ToggleVisibility,
+    ToggleHighlight,
+    ToggleCompact,
+    ToggleSortKeys,
+    ToggleRaw,
+    ToggleWrap,
+    PanLeft,
+    PanRight,
+    ToggleMultiline,
+    ToggleGroupDigits,
+    ToggleTimestamps,
+    ToggleGuides,
+    Search,
+    SearchKey,
+    NextMatch,
+    PreviousMatch,
+    ToggleResults,
+    GoTo,
+    Edit,
+    EditExternally,
+    Undo,
+    Redo,
+    Add,
+    Rename,
+    Delete,
+    Details,
+    Bookmark,
+    NextBookmark,
+    Copy,
+    CopyPath,
+    CopyGron,
+    Pick,
+    Pipe,
+    Command,
+    NextTab,
+    PreviousTab,
+    /// Switches to the tab numbered by the digit pressed.
+    SelectTab,
+    Help,
+    Quit,
+}
+
+/// Default keys of an action, and how it is named in the config file and listed in the help.
+#[derive(Debug)]
+pub struct Binding {
+    pub name: &'static str,
+    pub label: &'static str,
+    pub keys: &'static [KeyCode],
+    /// Held with each of the keys.
+    pub modifiers: KeyModifiers,
+    pub action: Action,
+    pub description: &'static str,
+}
+
+const fn bind(
+    name: &'static str,
+    label: &'static str,
+    keys: &'static [KeyCode],
+    action: Action,
+    description: &'static str,
+) -> Binding {
+    Binding {
+        name,
+        label,
+        keys,
+        modifiers: KeyModifiers::NONE,
+        action,
+        description,
+    }
+}
+
+impl Binding {
+    /// The binding with its keys pressed with Ctrl.
+    const fn ctrl(self) -> Self {
+        Self {
+            modifiers: KeyModifiers::CONTROL,
+            ..self
+        }
+    }
+
+    fn default_keys(&self) -> impl Iterator<Item = Key> {
+        self.keys.iter().map(|code| Key {
+            code: *code,
+            modifiers: self.modifiers,
+        })
+    }
+}
+
+/// Every action of the tree view with its default keys, in the order the help lists them.
+pub const KEYMAP: &[Binding] = &[
+    bind(
+        "move_up",
+        "Up",
+        &[KeyCode::Up],
+        Action::MoveUp,
+        "move the selection up",
+    ),
+    bind(
+        "move_down",
+        "Down",
+        &[KeyCode::Down],
+        Action::MoveDown,
+        "move the selection down",
+    ),
+    bind(
+        "center",
+        "z",
+        &[KeyCode::Char('z')],
+        Action::Center,
+        "scroll the selection to the middle of the view",
+    ),
+    bind(
+        "collapse",
+        "Enter",
+        &[KeyCode::Enter],
+        Action::ToggleVisibility,
+        "collapse/expand, or show a long string in full",
+    ),
+    bind(
+        "highlight",
+        "h",
+        &[KeyCode::Char('h')],
+        Action::ToggleHighlight,
+        "toggle highlighting",
+    ),
+    bind(
+        "compact",
+        "c",
+        &[KeyCode::Char('c')],
+        Action::ToggleCompact,
+        "toggle compact mode",
+    ),
+    bind(
+        "sort_keys",
+        "s",
+        &[KeyCode::Char('s')],
+        Action::ToggleSortKeys,
+        "toggle sorting object keys",
+    ),
+    bind(
+        "raw",
+        "R",
+        &[KeyCode::Char('R')],
+        Action::ToggleRaw,
+        "switch between the tree and the raw input",
+    ),
+    bind(
+        "wrap",
+        "w",
+        &[KeyCode::Char('w')],
+        Action::ToggleWrap,
+        "toggle wrapping long lines",
+    ),
+    bind(
+        "pan_left",
+        "Left",
+        &[KeyCode::Left],
+        Action::PanLeft,
+        "scroll left when not wrapping",
+    ),
+    bind(
+        "pan_right",
+        "Right",
+        &[KeyCode::Right],
+        Action::PanRight,
+        "scroll right when not wrapping",
+    ),
+    bind(
+        "multiline",
+        "m",
+        &[KeyCode::Char('m')],
+        Action::ToggleMultiline,
+        "toggle showing the line breaks of strings",
+    ),
+    bind(
+        "group_digits",
+        ",",
+        &[KeyCode::Char(',')],
+        Action::ToggleGroupDigits,
+        "toggle thousands separators",
+    ),
+    bind(
+        "timestamps",
+        "t",
+        &[KeyCode::Char('t')],
+        Action::ToggleTimestamps,
+        "toggle dates after timestamps",
+    ),
+    bind(
+        "indent_guides",
+        "|",
+        &[KeyCode::Char('|')],
+        Action::ToggleGuides,
+        "toggle indent guides",
+    ),
+    bind(
+        "search",
+        "/",
+        &[KeyCode::Char('/')],
+        Action::Search,
+        "search keys and values",
+    ),
+    bind(
+        "search_key",
+        "*",
+        &[KeyCode::Char('*')],
+        Action::SearchKey,
+        "search entries sharing the selected key",
+    ),
+    bind(
+        "next_match",
+        "n",
+        &[KeyCode::Char('n')],
+        Action::NextMatch,
+        "jump to the next match",
+    ),
+    bind(
+        "previous_match",
+        "N",
+        &[KeyCode::Char('N')],
+        Action::PreviousMatch,
+        "jump to the previous match",
+    ),
+    bind(
+        "results",
+        "L",
+        &[KeyCode::Char('L')],
+        Action::ToggleResults,
+        "list the matches",
+    ),
+    bind(
+        "go_to",
+        "g",
+        &[KeyCode::Char('g')],
+        Action::GoTo,
+        "go to a path such as data.items[5].name",
+    ),
+    bind(
+        "edit",
+        "e",
+        &[KeyCode::Char('e')],
+        Action::Edit,
+        "edit the selected value",
+    ),
+    bind(
+        "edit_externally",
+        "E",
+        &[KeyCode::Char('E')],
+        Action::EditExternally,
+        "edit the selected node in $EDITOR",
+    ),
+    bind(
+        "undo",
+        "u",
+        &[KeyCode::Char('u')],
+        Action::Undo,
+        "undo the last edit",
+    ),
+    bind(
+        "redo",
+        "C-r",
+        &[KeyCode::Char('r')],
+        Action::Redo,
+        "redo the last undone edit",
+    )
+    .ctrl(),
+    bind(
+        "add",
+        "a",
+        &[KeyCode::Char('a')],
+        Action::Add,
+        "add a value, or a key = value entry, after the selected node",
+    ),
+    bind(
+        "rename",
+        "r",
+        &[KeyCode::Char('r')],
+        Action::Rename,
+        "rename the key of the selected entry",
+    ),
+    bind(
+        "delete",
+        "x",
+        &[KeyCode::Char('x')],
+        Action::Delete,
+        "delete the selected node",
+    ),
+    bind(
+        "details",
+        "d",
+        &[KeyCode::Char('d')],
+        Action::Details,
+        "show details about the selected node",
+    ),
+    bind(
+        "bookmark",
+        "b",
+        &[KeyCode::Char('b')],
+        Action::Bookmark,
+        "bookmark the selected node, or remove its bookmark",
+    ),
+    bind(
+        "next_bookmark",
+        "'",
+        &[KeyCode::Char('\'')],
+        Action::NextBookmark,
+        "jump to the next bookmark",
+    ),
+    bind(
+        "copy",
+        "y",
+        &[KeyCode::Char('y')],
+        Action::Copy,
+        "copy the selected value",
+    ),
+    bind(
+        "copy_path",
+        "Y",
+        &[KeyCode::Char('Y')],
+        Action::CopyPath,
+        "copy the selected path, pressed again for another style",
+    ),
+    bind(
+        "copy_gron",
+        "G",
+        &[KeyCode::Char('G')],
+        Action::CopyGron,
+        "copy the selected value as gron assignments",
+    ),
+    bind(
+        "pick",
+        "o",
+        &[KeyCode::Char('o')],
+        Action::Pick,
+        "quit, printing the selected value",
+    ),
+    bind(
+        "pipe",
+        "!",
+        &[KeyCode::Char('!')],
+        Action::Pipe,
+        "pipe the selected JSON through a non-interactive shell command",
+    ),
+    bind(
+        "command",
+        ":",
+        &[KeyCode::Char(':')],
+        Action::Command,
+        "run a command: w <file> writes the document, ws <file> the selected node",
+    ),
+    bind(
+        "next_tab",
+        "Tab",
+        &[KeyCode::Tab],
+        Action::NextTab,
+        "switch to the next file",
+    ),
+    bind(
+        "previous_tab",
+        "S-Tab",
+        &[KeyCode::BackTab],
+        Action::PreviousTab,
+        "switch to the previous file",
+    ),
+    bind(
+        "select_tab",
+        "1-9",
+        &[
+            KeyCode::Char('1'),
+            KeyCode::Char('2'),
+            KeyCode::Char('3'),
+            KeyCode::Char('4'),
+            KeyCode::Char('5'),
+            KeyCode::Char('6'),
+            KeyCode::Char('7'),
+            KeyCode::Char('8'),
+            KeyCode::Char('9'),
+        ],
+        Action::SelectTab,
+        "switch to the file with that number",
+    ),
+    bind(
+        "help",
+        "?",
+        &[KeyCode::Char('?')],
+        Action::Help,
+        "show this help",
+    ),
+    bind(
+        "quit",
+        "q/Esc",
+        &[KeyCode::Char('q'), KeyCode::Esc],
+        Action::Quit,
+        "quit, also done with Ctrl-C",
+    ),
+];
+
+impl FromStr for Action {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match KEYMAP.iter().find(|b| b.name == s) {
+            // the digit pressed picks the tab, other keys would have nothing to switch to
+            Some(b) if b.action == Action::SelectTab => Err(format!(
+                "`{s}` is bound to the digits and cannot be remapped"
+            )),
+            Some(b) => Ok(b.action),
+            None => Err(format!(
+                "unknown action `{s}`, expected one of: {}",
+                KEYMAP
+                    .iter()
+                    .filter(|b| b.action != Action::SelectTab)
+                    .map(|b| b.name)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Action {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+/// A key with its modifiers, as written in the config file, e.g. `j`, `ctrl-d` or `shift-enter`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Key {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl Key {
+    const NAMED: [(&str, KeyCode); 13] = [
+        ("enter", KeyCode::Enter),
+        ("esc", KeyCode::Esc),
+        ("tab", KeyCode::Tab),
+        ("backtab", KeyCode::BackTab),
+        ("space", KeyCode::Char(' ')),
+        ("backspace", KeyCode::Backspace),
+        ("delete", KeyCode::Delete),
+        ("up", KeyCode::Up),
+        ("down", KeyCode::Down),
+        ("left", KeyCode::Left),
+        ("right", KeyCode::Right),
+        ("home", KeyCode::Home),
+        ("end", KeyCode::End),
+    ];
+
+    /// The key of a terminal event, shift being dropped where it is already part of the code,
+    /// as in `A` or `BackTab`.
+    pub fn from_event(event: KeyEvent) -> Self {
+        let modifiers = match event.code {
+            KeyCode::Char(_) | KeyCode::BackTab => event.modifiers - KeyModifiers::SHIFT,
+            _ => event.modifiers,
+        };
+
+        Self {
+            code: event.code,
+            modifiers: modifiers
+                & (KeyModifiers::SHIFT | KeyModifiers::CONTROL | KeyModifiers::ALT),
+        }
+    }
+}
+
+impl FromStr for Key {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = s;
+
+        // a trailing `-` is the key itself, as in `ctrl--`
+        while let Some((modifier, key)) = rest.split_once('-')
+            && !key.is_empty()
+        {
+            modifiers |= match modifier.to_lowercase().as_str() {
+                "ctrl" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return Err(format!("unknown modifier `{modifier}` in key `{s}`")),
+            };
+            rest = key;
+        }
+
+        let mut chars = rest.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => {
+                let name = rest.to_lowercase();
+                let function_key = name
+                    .strip_prefix('f')
+                    .and_then(|n| n.parse().ok())
+                    .filter(|n| (1..=12).contains(n));
+                match Self::NAMED.iter().find(|(n, _)| *n == name) {
+                    Some((_, code)) => *code,
+                    None => KeyCode::F(function_key.ok_or_else(|| format!("unknown key `{s}`"))?),
+                }
+            }
+        };
+
+        // shift is carried by the character or by BackTab
+        let key = match code {
+            KeyCode::Char(c) if modifiers.contains(KeyModifiers::SHIFT) => Key {
+                code: KeyCode::Char(c.to_ascii_uppercase()),
+                modifiers: modifiers - KeyModifiers::SHIFT,
+            },
+            KeyCode::Tab if modifiers.contains(KeyModifiers::SHIFT) => Key {
+                code: KeyCode::BackTab,
+                modifiers: modifiers - KeyModifiers::SHIFT,
+            },
+            code => Key { code, modifiers },
+        };
+
+        Ok(key)
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (modifier, prefix) in [
+            (KeyModifiers::CONTROL, "C-"),
+            (KeyModifiers::ALT, "A-"),
+            (KeyModifiers::SHIFT, "S-"),
+        ] {
+            if self.modifiers.contains(modifier) {
+                f.write_str(prefix)?;
+            }
+        }
+
+        match self.code {
+            KeyCode::Char(' ') => f.write_str("Space"),
+            KeyCode::Char(c) => write!(f, "{c}"),
+            KeyCode::BackTab => f.write_str("S-Tab"),
+            code => write!(f, "{code}"),
+        }
+    }
+}
+
+/// The keys of an action in the config file, a single one or a list.
+#[derive(Debug)]
+pub struct Keys(Vec<Key>);
+
+impl<'de> Deserialize<'de> for Keys {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum OneOrMany {
+            One(String),
+            Many(Vec<String>),
+        }
+
+        let keys = match OneOrMany::deserialize(deserializer)? {
+            OneOrMany::One(key) => vec![key],
+            OneOrMany::Many(keys) => keys,
+        };
+
+        keys.iter()
+            .map(|key| key.parse().map_err(de::Error::custom))
+            .collect::<Result<_, _>>()
+            .map(Keys)
+    }
+}
+
+/// The keys dispatched to each action, the defaults of [`KEYMAP`] with the config file's
+/// remappings applied.
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: Vec<(&'static Binding, Vec<Key>)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self {
+            bindings: KEYMAP
+                .iter()
+                .map(|b| (b, b.default_keys().collect()))
+                .collect(),
+        }
+    }
+}
+
+impl Keymap {
+    /// Replaces the keys of every action in `remapped`, returning warnings about keys that end
+    /// up bound to several actions. A default key taken by a remapped action is dropped from its
+    /// default action, while between remapped actions the first one listed in the help wins.
+    pub fn new(remapped: &BTreeMap<Action, Keys>) -> (Self, Vec<String>) {
+        let mut keymap = Self::default();
+        let mut warnings = vec![];
+
+        for (binding, keys) in &mut keymap.bindings {
+            if let Some(Keys(remapped)) = remapped.get(&binding.action) {
+                *keys = remapped.clone();
+            }
+        }
+
+        let taken: Vec<(Action, Key)> = remapped
+            .iter()
+            .flat_map(|(action, Keys(keys))| keys.iter().map(|key| (*action, *key)))
+            .collect();
+
+        for (binding, keys) in &mut keymap.bindings {
+            let own = remapped.contains_key(&binding.action);
+            for (action, key) in &taken {
+                if *action == binding.action || !keys.contains(key) {
+                    continue;
+                }
+
+                let other = KEYMAP.iter().find(|b| b.action == *action).unwrap().name;
+                if own {
+                    // reported once, from the action that wins
+                    if binding.action < *action {
+                        warnings.push(format!(
+                            "`{key}` is bound to both {} and {other}, {} is used",
+                            binding.name, binding.name
+                        ));
+                    }
+                } else {
+                    warnings.push(format!(
+                        "`{key}` now does {other} instead of {}",
+                        binding.name
+                    ));
+                    keys.retain(|k| k != key);
+                }
+            }
+        }
+
+        (keymap, warnings)
+    }
+
+    pub fn action(&self, event: KeyEvent) -> Option<Action> {
+        let key = Key::from_event(event);
+        self.bindings
+            .iter()
+            .find(|(_, keys)| keys.contains(&key))
+            .map(|(binding, _)| binding.action)
+    }
+
+    /// One line per action, its keys then its description, aligned in two columns.
+    pub fn help_lines(&self) -> Vec<String> {
+        let labels: Vec<String> = self
+            .bindings
+            .iter()
+            .map(|(binding, keys)| {
+                let default = keys.iter().copied().eq(binding.default_keys());
+                if default {
+                    binding.label.to_string()
+                } else if keys.is_empty() {
+                    "-".to_string()
+                } else {
+                    keys.iter()
+                        .map(Key::to_string)
+                        .collect::<Vec<_>>()
+                        .join("/")
+                }
+            })
+            .collect();
+
+        let width = labels.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+        labels
+            .iter()
+            .zip(&self.bindings)
+            .map(|(label, (binding, _))| format!("{label:width$}  {}", binding.description))
+            .collect()
+    }
+}
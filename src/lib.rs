@@ -0,0 +1,24 @@
+//! A JSON document held as a tree of foldable nodes, with a movable selection, rendered to
+//! [`ratatui`] text. This is the model behind the `json_tui` viewer and can be used on its own:
+//!
+//! ```
+//! use json_tui::Tree;
+//!
+//! let mut tree = Tree::from_value(serde_json::json!({"a": [1, 2]}));
+//! tree.next_node_down();
+//! assert_eq!(tree.find_current_line(), 1);
+//! assert_eq!(tree.to_text().lines.len(), 6);
+//! ```
+
+pub mod diff;
+pub mod error;
+mod history;
+pub mod node;
+pub mod path;
+pub mod source;
+pub mod theme;
+mod timestamp;
+
+pub use error::{EditError, TreeError};
+pub use node::{Node, Tree};
+pub use slotmap::DefaultKey;
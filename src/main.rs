@@ -1,148 +1,1653 @@
-mod node;
+mod clipboard;
+mod command;
+mod config;
+mod editor;
+mod fetch;
+mod follow;
+mod input;
+mod keymap;
+mod pipe;
+mod popup;
+mod prompt;
+mod results;
+mod screen;
+mod search;
+mod session;
+mod tab;
+mod wrap;
 
-use crate::node::Tree;
+use std::{
+    io::Read,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, Sender},
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    clipboard::Clipboard,
+    command::Command,
+    config::Config,
+    input::{Compression, Encoding, Format, InvalidInput, Leniency},
+    keymap::{Action, Keymap},
+    pipe::Pipe,
+    popup::{PopupScroll, render_popup, render_scrollable_popup},
+    prompt::{Prompt, PromptKind, parse_entry},
+    screen::Screen,
+    session::Session,
+    tab::Tab,
+};
+
+use json_tui::{
+    DefaultKey, EditError, diff,
+    node::{Indent, Tree, value_type},
+    path::{PathStyle, format_path, parse_path},
+    source,
+    theme::Theme,
+};
+use serde_json::Value;
 
 use color_eyre::{Result, eyre::eyre};
-use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use crossterm::event::{
+    self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseButton, MouseEventKind,
+};
 use ratatui::{
-    DefaultTerminal, Frame,
+    Frame,
     layout::{Alignment, Constraint, Layout, Rect},
     style::Stylize,
-    widgets::{Block, Borders, Paragraph, Wrap},
+    text::{Line, Text},
+    widgets::{Block, Borders, Paragraph},
 };
-use serde_json::Value;
 
-fn retrieve_content() -> Result<(String, String)> {
-    let args: Vec<String> = std::env::args().collect();
+const USAGE: &str = "\
+Usage: json_tui [OPTIONS] [FILE]...
+
+Views JSON files as a foldable tree, reading standard input when no file is given or for -.
+Arguments after -- are files, even when named like options, and http:// or https:// ones are
+fetched.
+
+Options:
+  --compact              start in compact mode
+  --sort-keys            start with object keys sorted
+  --format <FORMAT>      input format: json, ndjson, yaml or toml (guessed from the extension
+                         otherwise)
+  --url <URL>            fetch the input from URL, with curl
+  --header <HEADER>      send \"Name: value\" along when fetching URLs
+  --skip-invalid         leave out invalid lines of NDJSON input rather than failing
+  --lenient              accept JSON with comments, trailing commas and unquoted keys
+  --encoding <ENCODING>  input encoding: utf-8, utf-16le, utf-16be or latin1 (UTF-8 unless a
+                         byte order mark says otherwise)
+  --lossy                replace bytes invalid in the encoding with � rather than failing
+  --indent <N|tabs>      indent by N spaces (2 by default) or by tabs
+  --depth <N>            start with containers N levels deep collapsed, 0 collapsing the root
+  --collapse-arrays <N>  start with arrays of more than N numbers, strings, booleans or nulls
+                         collapsed
+  --theme <dark|light>   colour theme
+  --no-color             disable colours, also done when NO_COLOR is set
+  --diff <A> <B>         show two files side by side with their differences coloured
+  --session              restore the collapsed objects and cursor from the last time
+  --follow               keep reading NDJSON input as it grows, as tail -f does
+  --max-size <MB>        ask before parsing inputs larger than MB megabytes (256 by default,
+                         0 for no limit)
+  --output <FILE>        write the input to FILE as pretty-printed JSON and exit
+  --pick                 exit with status 1 unless a node is picked with o
+  --print-path           print the path of the picked node rather than its value
+  -h, --help             print this help and exit
+  -V, --version          print the version and exit
+";
 
-    let (title, data) = match args.len() {
-        1 => {
-            let data = std::io::read_to_string(std::io::stdin())?;
-            ("stdin".to_string(), data)
+// longest time between the clicks of a double click
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+// containers with more children are only deleted once confirmed
+const DELETE_CONFIRM_CHILDREN: usize = 10;
+// the path standing for stdin
+const STDIN: &str = "-";
+// inputs of more megabytes are only parsed once confirmed, as parsing takes many times their size
+const DEFAULT_MAX_SIZE: u64 = 256;
+const MEGABYTE: u64 = 1024 * 1024;
+// lines of followed input added per draw, so that a big file does not hold up the interface
+const FOLLOW_BATCH: usize = 10_000;
+const SPINNER_INTERVAL: Duration = Duration::from_millis(100);
+// exit status of an input failing to parse, 1 being that of quitting without a pick
+const EXIT_INVALID: i32 = 2;
+
+#[derive(Debug, Default, Clone)]
+struct Options {
+    paths: Vec<String>,
+    compact: bool,
+    sort_keys: bool,
+    session: bool,
+    diff: bool,
+    pick: bool,
+    print_path: bool,
+    theme: Option<String>,
+    format: Option<Format>,
+    encoding: Option<Encoding>,
+    /// Replaces invalid bytes rather than failing.
+    lossy: bool,
+    output: Option<String>,
+    no_color: bool,
+    indent: Indent,
+    collapse_arrays: Option<usize>,
+    /// Containers this many levels deep start collapsed.
+    depth: Option<usize>,
+    leniency: Leniency,
+    /// Sent along when fetching URLs.
+    headers: Vec<String>,
+    /// Inputs of more megabytes are only parsed once confirmed, 0 lifting the limit.
+    max_size: Option<u64>,
+    follow: bool,
+}
+
+impl Options {
+    fn from_args() -> Result<Self> {
+        let mut options = Options::default();
+
+        let mut args = std::env::args().skip(1);
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-h" | "--help" => {
+                    print!("{USAGE}\nKeys:\n");
+                    for line in Keymap::default().help_lines() {
+                        println!("  {line}");
+                    }
+                    println!(
+                        "\nThe mouse wheel scrolls, clicks select and double clicks collapse/expand."
+                    );
+                    std::process::exit(0);
+                }
+                "-V" | "--version" => {
+                    println!("json_tui {}", env!("CARGO_PKG_VERSION"));
+                    std::process::exit(0);
+                }
+                "--compact" => options.compact = true,
+                "--sort-keys" => options.sort_keys = true,
+                "--session" => options.session = true,
+                "--follow" => options.follow = true,
+                "--diff" => options.diff = true,
+                "--pick" => options.pick = true,
+                "--print-path" => options.print_path = true,
+                "--no-color" => options.no_color = true,
+                "--skip-invalid" => options.leniency.skip_invalid = true,
+                "--lenient" => options.leniency.lenient = true,
+                "--lossy" => options.lossy = true,
+                "--format" => {
+                    let name = args
+                        .next()
+                        .ok_or_else(|| eyre!("--format expects a name"))?;
+                    options.format = Some(Format::from_name(&name)?);
+                }
+                "--encoding" => {
+                    let name = args
+                        .next()
+                        .ok_or_else(|| eyre!("--encoding expects a name"))?;
+                    options.encoding = Some(Encoding::from_name(&name)?);
+                }
+                "--indent" => {
+                    let indent = args.next().unwrap_or_default();
+                    options.indent = match indent.as_str() {
+                        "tabs" => Indent::Tab,
+                        n => match n.parse() {
+                            Ok(n @ 1..=8) => Indent::Spaces(n),
+                            _ => {
+                                return Err(eyre!(
+                                    "--indent expects a number of spaces from 1 to 8 or \"tabs\""
+                                ));
+                            }
+                        },
+                    };
+                }
+                "--depth" => {
+                    let depth = args
+                        .next()
+                        .and_then(|n| n.parse().ok())
+                        .ok_or_else(|| eyre!("--depth expects a number of levels"))?;
+                    options.depth = Some(depth);
+                }
+                "--collapse-arrays" => {
+                    let threshold = args
+                        .next()
+                        .and_then(|n| n.parse().ok())
+                        .ok_or_else(|| eyre!("--collapse-arrays expects a number of items"))?;
+                    options.collapse_arrays = Some(threshold);
+                }
+                "--max-size" => {
+                    let size = args
+                        .next()
+                        .and_then(|n| n.parse().ok())
+                        .ok_or_else(|| eyre!("--max-size expects a number of megabytes"))?;
+                    options.max_size = Some(size);
+                }
+                "--url" => {
+                    let url = args
+                        .next()
+                        .filter(|url| fetch::is_url(url))
+                        .ok_or_else(|| eyre!("--url expects an http:// or https:// address"))?;
+                    options.paths.push(url);
+                }
+                "--header" => {
+                    let header = args
+                        .next()
+                        .filter(|header| header.contains(':'))
+                        .ok_or_else(|| eyre!("--header expects \"Name: value\""))?;
+                    options.headers.push(header);
+                }
+                "--output" => {
+                    let path = args
+                        .next()
+                        .ok_or_else(|| eyre!("--output expects a file name"))?;
+                    options.output = Some(path);
+                }
+                "--theme" => {
+                    let name = args.next().ok_or_else(|| eyre!("--theme expects a name"))?;
+                    options.theme = Some(name);
+                }
+                // files named like options
+                "--" => options.paths.extend(args.by_ref()),
+                option if option.starts_with('-') && option != STDIN => {
+                    return Err(eyre!("unknown option {option}, see --help"));
+                }
+                _ => options.paths.push(arg),
+            }
         }
-        2 => {
-            let data = std::fs::read_to_string(&args[1])?;
-            (args[1].to_string(), data)
+
+        if options.paths.iter().filter(|path| *path == STDIN).count() > 1 {
+            return Err(eyre!("stdin can only be read once"));
+        }
+
+        if options.diff && options.paths.len() != 2 {
+            return Err(eyre!("--diff expects exactly two files"));
+        }
+
+        if options.output.is_some() && (options.diff || options.paths.len() > 1) {
+            return Err(eyre!("--output expects a single input"));
         }
-        _ => {
-            println!("More than 1 arg not supported");
-            return Err(eyre!("More than 1 arg not supported"));
+
+        if options.follow {
+            if options.diff || options.output.is_some() {
+                return Err(eyre!("--follow cannot be combined with --diff or --output"));
+            }
+            if options.paths.len() > 1 || options.paths.iter().any(|path| fetch::is_url(path)) {
+                return Err(eyre!("--follow expects a single file, or stdin"));
+            }
+            if options
+                .format
+                .is_some_and(|format| format != Format::Ndjson)
+            {
+                return Err(eyre!("--follow reads NDJSON, one value per line"));
+            }
         }
+
+        Ok(options)
+    }
+}
+
+/// An input read and parsed, to be opened in a tab.
+struct Document {
+    title: String,
+    /// The file read, `None` for stdin.
+    path: Option<String>,
+    content: String,
+    tree: Tree,
+    /// What was left out of the input, such as invalid lines of NDJSON.
+    warnings: Vec<String>,
+    /// Why the input could not be parsed, the tree being empty.
+    error: Option<String>,
+}
+
+/// An input over the size limit, which `confirm` is asked about before it is parsed.
+#[derive(Debug, Clone)]
+struct Oversized {
+    title: String,
+    size: u64,
+    limit: u64,
+    /// Whether the size is that of the input once decompressed.
+    decompressed: bool,
+}
+
+impl std::fmt::Display for Oversized {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let decompressed = if self.decompressed {
+            " once decompressed"
+        } else {
+            ""
+        };
+        write!(
+            f,
+            "{} is {}{decompressed}, over the limit of {}",
+            self.title,
+            format_size(self.size),
+            format_size(self.limit)
+        )
+    }
+}
+
+/// An input as read, before parsing.
+struct Input {
+    title: String,
+    /// The file read, `None` for stdin and URLs.
+    path: Option<String>,
+    content: String,
+    /// Set when invalid bytes were replaced, see `--lossy`.
+    warning: Option<String>,
+}
+
+/// Reads every input. Stdin is read when no path is given or for `-`. Inputs over the size limit
+/// are left unparsed unless `confirm` agrees, files being checked before they are even read.
+/// Compressed inputs are decompressed, and checked again unless already agreed to.
+fn retrieve_content(options: &Options, confirm: &dyn Fn(&Oversized) -> bool) -> Result<Vec<Input>> {
+    if options.paths.is_empty() {
+        return Ok(vec![read_stdin(options, confirm)?]);
+    }
+
+    options
+        .paths
+        .iter()
+        .map(|path| {
+            if path == STDIN {
+                return read_stdin(options, confirm);
+            }
+            if fetch::is_url(path) {
+                let data = fetch::fetch(path, &options.headers)?;
+                let agreed = check_size(options, path, data.len() as u64, false, confirm)?;
+                let (content, warning) = unpack(options, path, data, agreed, confirm)?;
+                return Ok(Input {
+                    title: path.to_string(),
+                    path: None,
+                    content,
+                    warning,
+                });
+            }
+            let size = std::fs::metadata(path)
+                .map_err(|e| eyre!("{path}: {e}"))?
+                .len();
+            let agreed = check_size(options, path, size, false, confirm)?;
+            let data = std::fs::read(path).map_err(|e| eyre!("{path}: {e}"))?;
+            let (content, warning) = unpack(options, path, data, agreed, confirm)?;
+            Ok(Input {
+                title: path.to_string(),
+                path: Some(path.to_string()),
+                content,
+                warning,
+            })
+        })
+        .collect()
+}
+
+fn read_stdin(options: &Options, confirm: &dyn Fn(&Oversized) -> bool) -> Result<Input> {
+    let mut data = vec![];
+    std::io::stdin().read_to_end(&mut data)?;
+    let agreed = check_size(options, "stdin", data.len() as u64, false, confirm)?;
+    let (content, warning) = unpack(options, "stdin", data, agreed, confirm)?;
+    Ok(Input {
+        title: "stdin".to_string(),
+        path: None,
+        content,
+        warning,
+    })
+}
+
+/// Decompresses input `title` if compressed, checking its size once decompressed unless
+/// `agreed` to already, and decodes it, along with a warning if invalid bytes were replaced.
+fn unpack(
+    options: &Options,
+    title: &str,
+    data: Vec<u8>,
+    agreed: bool,
+    confirm: &dyn Fn(&Oversized) -> bool,
+) -> Result<(String, Option<String>)> {
+    let data = match Compression::detect(&data) {
+        Some(compression) => {
+            let data = input::decompress(title, compression, data)?;
+            if !agreed {
+                check_size(options, title, data.len() as u64, true, confirm)?;
+            }
+            data
+        }
+        None => data,
     };
+    input::decode(title, data, options.encoding, options.lossy)
+}
+
+/// Fails for input `title` of `size` bytes when over the limit and not confirmed, returning
+/// whether it was confirmed.
+fn check_size(
+    options: &Options,
+    title: &str,
+    size: u64,
+    decompressed: bool,
+    confirm: &dyn Fn(&Oversized) -> bool,
+) -> Result<bool> {
+    let limit = options
+        .max_size
+        .unwrap_or(DEFAULT_MAX_SIZE)
+        .saturating_mul(MEGABYTE);
+    if limit == 0 || size <= limit {
+        return Ok(false);
+    }
 
-    Ok((title, data))
+    let oversized = Oversized {
+        title: title.to_string(),
+        size,
+        limit,
+        decompressed,
+    };
+    if confirm(&oversized) {
+        Ok(true)
+    } else {
+        Err(eyre!(
+            "{oversized}; --max-size or max_size in the config file raises it"
+        ))
+    }
+}
+
+/// Writes a number of bytes in the largest binary unit it reaches, e.g. `1.5 GiB`.
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+
+    if bytes < 1024 {
+        return format!("{bytes} bytes");
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{size:.1} {}", UNITS[unit])
+}
+
+/// Reads and parses every input, returning its title, content and tree. Among several files,
+/// those failing to parse are kept with their error, to be shown in their tab, unless all do.
+fn load_documents(
+    options: &Options,
+    confirm: &dyn Fn(&Oversized) -> bool,
+) -> Result<Vec<Document>> {
+    // followed input is read line by line once the interface is up
+    if options.follow {
+        let path = options.paths.first().filter(|path| *path != STDIN).cloned();
+        return Ok(vec![Document {
+            title: path.clone().unwrap_or_else(|| "stdin".to_string()),
+            path,
+            content: String::new(),
+            tree: build_tree(Value::Array(vec![]), options.depth),
+            warnings: vec![],
+            error: None,
+        }]);
+    }
+
+    let contents = retrieve_content(options, confirm)?;
+    let several = contents.len() > 1 && !options.diff && options.output.is_none();
+    let mut first_error = None;
+
+    let documents: Vec<Document> = contents
+        .into_iter()
+        .map(
+            |Input {
+                 title,
+                 path,
+                 content,
+                 warning,
+             }| {
+                let format = options
+                    .format
+                    .unwrap_or_else(|| Format::detect(path.as_deref()));
+                let parsed = match input::parse(&title, format, &content, options.leniency) {
+                    Ok(parsed) => parsed,
+                    Err(e) if several => {
+                        let error = e.to_string();
+                        first_error.get_or_insert(e);
+                        return Ok(Document {
+                            title,
+                            path,
+                            content,
+                            tree: Tree::from_value(Value::Null),
+                            warnings: warning.into_iter().collect(),
+                            error: Some(error),
+                        });
+                    }
+                    Err(e) => return Err(e),
+                };
+                let mut tree = build_tree(parsed.value, options.depth);
+                // YAML and TOML are not traced back to their text, nor NDJSON once lines are left out,
+                // and JSON only up to what was left out of it
+                let lines = format == Format::Ndjson;
+                let text = match &parsed.trailing {
+                    None => Some(content.as_str()),
+                    Some(trailing) if !options.leniency.lenient => content.get(..trailing.offset),
+                    Some(_) => None,
+                };
+                if let Some(text) = text
+                    && (format == Format::Json || lines && parsed.skipped == 0)
+                {
+                    source::record_spans(&mut tree, text, lines);
+                }
+
+                let mut warnings: Vec<String> = warning.into_iter().collect();
+                if parsed.skipped > 0 {
+                    let lines = if parsed.skipped == 1 { "line" } else { "lines" };
+                    warnings.push(format!(
+                        "{title}: skipped {} invalid {lines}",
+                        parsed.skipped
+                    ));
+                }
+                if let Some(trailing) = parsed.trailing {
+                    warnings.push(format!("{title}: {trailing}"));
+                }
+
+                Ok(Document {
+                    title,
+                    path,
+                    content,
+                    tree,
+                    warnings,
+                    error: None,
+                })
+            },
+        )
+        .collect::<Result<_>>()?;
+
+    match first_error {
+        Some(e) if documents.iter().all(|document| document.error.is_some()) => Err(e),
+        _ => Ok(documents),
+    }
+}
+
+/// Builds the tree of `value`, collapsed past `depth` levels when given, which leaves those
+/// containers to be built once expanded.
+fn build_tree(value: Value, depth: Option<usize>) -> Tree {
+    match depth {
+        Some(depth) => Tree::from_value_to_depth(value, depth),
+        None => Tree::from_value(value),
+    }
+}
+
+/// Inputs read and parsed on another thread, which waits for an answer on `answers` whenever
+/// it sends an input over the size limit on `oversized`.
+struct Loading<T> {
+    thread: JoinHandle<Result<T>>,
+    oversized: Receiver<Oversized>,
+    answers: Sender<bool>,
+}
+
+/// Shows a spinner until `loading` is done, and a popup asking whether to parse any input over
+/// the size limit. Returns `None` when the user quits first.
+fn wait_for<T>(terminal: &mut Screen, loading: Loading<T>) -> Result<Option<T>> {
+    const SPINNER: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+    let mut asking: Option<Oversized> = None;
+
+    for frame_index in 0.. {
+        if loading.thread.is_finished() {
+            break;
+        }
+        if asking.is_none() {
+            asking = loading.oversized.try_recv().ok();
+        }
+
+        terminal.draw(|frame| {
+            let spinner = SPINNER[frame_index % SPINNER.len()];
+            let [_, area, _] = Layout::vertical([
+                Constraint::Fill(1),
+                Constraint::Length(1),
+                Constraint::Fill(1),
+            ])
+            .areas(frame.area());
+            frame.render_widget(
+                Paragraph::new(format!("{spinner} Loading…")).alignment(Alignment::Center),
+                area,
+            );
+
+            if let Some(oversized) = &asking {
+                let text = Text::raw(format!("{oversized}.\n\nParse it anyway? (y/n)"));
+                render_popup(frame, frame.area(), "large input", text);
+            }
+        })?;
+
+        if !event::poll(SPINNER_INTERVAL)? {
+            continue;
+        }
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+
+        if is_interrupt(key) {
+            return Ok(None);
+        }
+        if asking.is_some() {
+            let answer = match key.code {
+                KeyCode::Char('y') => true,
+                KeyCode::Char('n') | KeyCode::Esc => false,
+                _ => continue,
+            };
+            asking = None;
+            let _ = loading.answers.send(answer);
+            continue;
+        }
+        if matches!(key.code, KeyCode::Char('q') | KeyCode::Esc) {
+            return Ok(None);
+        }
+    }
+
+    match loading.thread.join() {
+        Ok(result) => result.map(Some),
+        Err(panic) => std::panic::resume_unwind(panic),
+    }
 }
 
 fn main() -> Result<()> {
     color_eyre::install()?;
-    let (title, content) = retrieve_content()?;
+    let mut options = Options::from_args()?;
+    // see https://no-color.org
+    let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+    let mut config = config::load(options.theme.as_deref())?;
+    if options.no_color || no_color {
+        config.theme = Theme::monochrome();
+    }
+    options.max_size = options.max_size.or(config.max_size);
 
-    let terminal = ratatui::init();
-    let result = run(terminal, title, content);
-    ratatui::restore();
+    // converting needs no interface
+    if let Some(output) = &options.output {
+        for Document { tree, warnings, .. } in
+            exit_if_invalid(load_documents(&options, &|_| false))?
+        {
+            for warning in warnings {
+                eprintln!("warning: {warning}");
+            }
+            let json = command::to_json(&tree, tree.root(), options.indent);
+            command::write_json(Path::new(output), &json, true)?;
+        }
+        return Ok(());
+    }
+
+    let terminal = screen::init()?;
+
+    // big inputs take a while to read and parse, the interface shows a spinner meanwhile
+    let (ask, oversized) = mpsc::channel();
+    let (answers, answered) = mpsc::channel();
+    let thread = std::thread::spawn({
+        let options = options.clone();
+        move || {
+            load_documents(&options, &|input: &Oversized| {
+                ask.send(input.clone()).is_ok() && answered.recv().unwrap_or(false)
+            })
+        }
+    });
+    let loading = Loading {
+        thread,
+        oversized,
+        answers,
+    };
+
+    let result = run(terminal, loading, &options, config);
+    screen::restore()?;
+
+    // printed once off the alternate screen, for scripts reading the pick
+    match exit_if_invalid(result)? {
+        Some(picked) => println!("{picked}"),
+        None if options.pick => std::process::exit(1),
+        None => (),
+    }
+
+    Ok(())
+}
+
+/// Reports input failing to parse on its own, without the trace of other errors, and exits
+/// with [`EXIT_INVALID`].
+fn exit_if_invalid<T>(result: Result<T>) -> Result<T> {
+    if let Err(e) = &result
+        && let Some(invalid) = e.downcast_ref::<InvalidInput>()
+    {
+        eprintln!("{invalid}");
+        std::process::exit(EXIT_INVALID);
+    }
     result
 }
 
-fn run(mut terminal: DefaultTerminal, title: String, content: String) -> Result<()> {
-    let content: Value = serde_json::from_str(&content)?;
-    let mut tree = Tree::from_value(content);
+fn run(
+    mut terminal: Screen,
+    loading: Loading<Vec<Document>>,
+    options: &Options,
+    mut config: Config,
+) -> Result<Option<String>> {
+    let Some(documents) = wait_for(&mut terminal, loading)? else {
+        return Ok(None);
+    };
+    let mut tabs = vec![];
+
+    for Document {
+        title,
+        path,
+        content,
+        mut tree,
+        warnings,
+        error,
+    } in documents
+    {
+        config.warnings.extend(warnings);
+
+        tree.set_compact(options.compact);
+        tree.set_sort_keys(options.sort_keys);
+        tree.set_indent(options.indent);
+        tree.set_theme(config.theme.clone());
+        tree.set_collapsed_preview(config.collapsed_preview);
+        tree.set_quote_style(config.quotes);
+        tree.set_select_expanded(config.select_expanded);
+        tree.set_focus_mode(config.focus_mode);
+        if let Some(limit) = config.string_limit {
+            tree.set_string_limit(limit);
+        }
+        if let Some(depth) = config.undo_depth {
+            tree.set_undo_depth(depth);
+        }
+        if let Some(threshold) = options.collapse_arrays.or(config.collapse_arrays) {
+            tree.auto_collapse_scalar_arrays(threshold);
+        }
+
+        // sessions are only kept for files, stdin has nothing stable to key them on
+        let session_file = match &path {
+            Some(path) if options.session && error.is_none() => Some(session::state_file(path)?),
+            _ => None,
+        };
+
+        if let Some(session) = session_file.as_deref().and_then(session::load) {
+            session.apply(&mut tree);
+        }
 
-    let scroll_x = 0;
-    let mut scroll_y: u16 = 0;
+        let path = path.map(PathBuf::from);
+        let mut tab = Tab::new(title, tree, content, path, session_file);
+        tab.error = error;
+        tabs.push(tab);
+    }
 
-    let mut up_clamp = 0;
-    let mut bot_clamp = 0;
-    let mut scroll_y_max = 0;
-    let mut total_height = 0;
+    if options.diff {
+        let [left, right] = &mut tabs[..] else {
+            unreachable!("--diff takes exactly two files");
+        };
+        diff::mark_differences(&mut left.tree, &mut right.tree);
+    }
+
+    // lines of followed input, added to the only tab as they come
+    let following = if options.follow {
+        let path = options.paths.first().filter(|path| *path != STDIN);
+        Some(follow::follow(path.map(String::as_str))?)
+    } else {
+        None
+    };
+    let mut skipped_lines = 0;
+
+    let mut active = 0;
+    let mut wrap = true;
+
+    let mut prompt: Option<Prompt> = None;
+    // a sense of the document's size until anything else is reported
+    let mut status = if config.warnings.is_empty() {
+        tabs[active].tree.stats().to_string()
+    } else {
+        config.warnings.join("; ")
+    };
+    let mut details: Option<Text> = None;
+    let mut help: Option<PopupScroll> = None;
+    // the command the selection is piped through, then its output
+    let mut pipe: Option<Pipe> = None;
+    let mut piped: Option<(String, Text, PopupScroll)> = None;
+    let mut confirming_quit = false;
+    // JSON left invalid by the editor, with its error, until edited again or dropped
+    let mut invalid_edit: Option<(String, String)> = None;
+    // the number of children of the container about to be deleted
+    let mut confirming_delete: Option<usize> = None;
+    // time and position of the last click, to tell double clicks
+    let mut last_click: Option<(Instant, u16, u16)> = None;
+    let mut clipboard = Clipboard::new();
+    let mut picked = None;
+    // the node and style of the path copied by the last key, the next copy of it cycling styles
+    let mut copied_path: Option<(DefaultKey, PathStyle)> = None;
+    // the selection and status last drawn, and whether the status previews the selection
+    let mut drawn: Option<(DefaultKey, String)> = None;
+    let mut previewing = false;
 
     loop {
-        let current_line = tree.find_current_line();
+        if let Some(lines) = &following {
+            let tab = &mut tabs[0];
+            for line in lines.try_iter().take(FOLLOW_BATCH) {
+                tab.append_raw(&line.text);
+                match line.value {
+                    // the view keeps up when the selection is on the last line
+                    Some(value) => tab.follow |= tab.tree.append_to_root(value) == Ok(true),
+                    None => {
+                        skipped_lines += 1;
+                        let lines = if skipped_lines == 1 { "line" } else { "lines" };
+                        status = format!("skipped {skipped_lines} invalid {lines}");
+                    }
+                }
+            }
+        }
 
-        let number_of_lines = {
-            let content_text = tree.to_text();
-            content_text.lines.len()
+        if let Some(running) = &mut pipe {
+            match running.is_finished() {
+                Ok(false) => (),
+                Ok(true) => {
+                    let running = pipe.take().expect("the pipe is running");
+                    let command = running.command.clone();
+                    match running.finish() {
+                        Ok(output) => {
+                            let text = output.to_text(config.theme.muted_style());
+                            piped = Some((command, text, PopupScroll::default()));
+                            status.clear();
+                        }
+                        Err(e) => status = format!("{command}: {e}"),
+                    }
+                }
+                Err(e) => {
+                    status = format!("{}: {e}", running.command);
+                    pipe.take().expect("the pipe is running").kill();
+                }
+            }
+        }
+
+        if options.diff {
+            // the other pane follows the selection when the same path exists there
+            let path = tabs[active].tree.current_path();
+            let other = &mut tabs[1 - active];
+            if let Some(k) = other.tree.find_path(&path)
+                && k != other.tree.current_key()
+            {
+                other.tree.select(k);
+                other.follow = true;
+            }
+        }
+
+        // a collapsed container the selection moves to is previewed in the footer, unless the
+        // move reported something else
+        let selected = tabs[active].tree.current_key();
+        if let Some((before, reported)) = &drawn {
+            let tree = &tabs[active].tree;
+            if *reported != status {
+                previewing = false;
+            } else if *before != selected {
+                if tree.is_collapsed(selected) {
+                    status = tree.compact_preview(selected, terminal.size()?.width.into());
+                    previewing = true;
+                } else if previewing {
+                    status.clear();
+                    previewing = false;
+                }
+            }
+        }
+        drawn = Some((selected, status.clone()));
+
+        let dirty = tabs.iter().any(|tab| tab.tree.is_dirty());
+        let tab_count = tabs.len();
+        let modified = if tabs[active].tree.is_dirty() {
+            " [+]"
+        } else {
+            ""
+        };
+        let title = if options.diff {
+            format!("{} → {}{modified}", tabs[0].title, tabs[1].title)
+        } else if tab_count > 1 {
+            format!(
+                "{} ({}/{tab_count}){modified}",
+                tabs[active].title,
+                active + 1
+            )
+        } else {
+            format!("{}{modified}", tabs[active].title)
         };
 
         terminal.draw(|frame| {
-            let (title_area, layout) = calculate_layout(frame.area());
-
-            (up_clamp, bot_clamp) = {
-                total_height = (layout.height as usize) - 2; // account for borders
-                let first_third = total_height / 3;
-                let second_third = first_third * 2;
-                let scroll_y = scroll_y as usize;
-                (first_third + scroll_y, second_third + scroll_y)
+            let (title_area, layout, status_area) = calculate_layout(frame.area());
+            let (layout, results_area) = if tabs[active].results.open {
+                split_results(layout)
+            } else {
+                (layout, Rect::default())
             };
 
-            scroll_y_max = number_of_lines.saturating_sub(total_height) as u16;
+            if options.diff {
+                let panes = Layout::horizontal([Constraint::Ratio(1, 2); 2]).areas::<2>(layout);
+                for (i, area) in panes.into_iter().enumerate() {
+                    let tab = &mut tabs[i];
+                    let block = Block::new().borders(Borders::ALL).title(tab.title.clone());
+                    let block = if i == active {
+                        block
+                    } else {
+                        block.style(tab.tree.theme().muted_style())
+                    };
+                    tab.render(frame, area, wrap, block);
+                }
+            } else {
+                tabs[active].render(frame, layout, wrap, Block::new().borders(Borders::ALL));
+            }
+
+            let tab = &mut tabs[active];
+            render_title(frame, title_area, &title, tab.tree.theme());
+
+            if let Some(prompt) = &prompt {
+                let line = match prompt.kind {
+                    PromptKind::Search => tab.search.prompt(&prompt.input),
+                    PromptKind::Edit => format!("edit: {}", prompt.input),
+                    PromptKind::GoTo => format!("go to: {}", prompt.input),
+                    PromptKind::Pipe => format!("pipe to a filter (no terminal): {}", prompt.input),
+                    PromptKind::Command => format!(":{}", prompt.input),
+                    PromptKind::Add if tab.tree.inserts_into_object() == Some(true) => {
+                        format!("add key = value: {}", prompt.input)
+                    }
+                    PromptKind::Add => format!("add value: {}", prompt.input),
+                    PromptKind::Rename => format!("rename to: {}", prompt.input),
+                };
+                render_status(
+                    frame,
+                    status_area,
+                    &prompt.with_error(line),
+                    tab.tree.theme(),
+                );
+            } else {
+                render_status(frame, status_area, &status, tab.tree.theme());
+            }
+
+            if tab.results.open {
+                tab.results
+                    .render(frame, results_area, &tab.tree, &tab.search);
+            }
+
+            if let Some(details) = &details {
+                render_popup(frame, frame.area(), "details", details.clone());
+            }
 
-            if scroll_y > scroll_y_max {
-                scroll_y = scroll_y_max;
+            if let Some(scroll) = &mut help {
+                let text = Text::from_iter(config.keymap.help_lines());
+                render_scrollable_popup(frame, frame.area(), "keys", text, scroll);
             }
 
-            render_title(frame, title_area, &title);
+            if let Some((command, text, scroll)) = &mut piped {
+                render_scrollable_popup(frame, frame.area(), command, text.clone(), scroll);
+            }
 
-            let text_content = tree.to_text();
+            if let Some(children) = confirming_delete {
+                let text = Text::raw(format!("Delete it and its {children} children? (y/n)"));
+                render_popup(frame, frame.area(), "delete", text);
+            }
 
-            let paragraph = Paragraph::new(text_content.clone())
-                .wrap(Wrap { trim: false })
-                .scroll((scroll_y, scroll_x))
-                .block(Block::new().borders(Borders::ALL));
+            if let Some((_, error)) = &invalid_edit {
+                let text = Text::raw(format!("{error}\n\nEdit it again? (y/n)"));
+                render_popup(frame, frame.area(), "invalid JSON", text);
+            }
 
-            frame.render_widget(paragraph, layout);
+            if confirming_quit {
+                let text = Text::raw("Save changes? (y/n/cancel)");
+                render_popup(frame, frame.area(), "unsaved changes", text);
+            }
         })?;
 
-        if let Event::Key(key) = event::read()?
+        // a running command and followed input are checked on between events
+        if (pipe.is_some() || following.is_some()) && !event::poll(SPINNER_INTERVAL)? {
+            continue;
+        }
+        let event = event::read()?;
+
+        // the mouse only acts on the trees, not on popups or the prompt
+        if let Event::Mouse(mouse) = event {
+            if details.is_some()
+                || help.is_some()
+                || piped.is_some()
+                || confirming_delete.is_some()
+                || invalid_edit.is_some()
+                || confirming_quit
+                || prompt.is_some()
+            {
+                continue;
+            }
+
+            let (column, row) = (mouse.column, mouse.row);
+            let Some(i) = (0..tabs.len())
+                .filter(|&i| options.diff || i == active)
+                .find(|&i| tabs[i].contains(column, row))
+            else {
+                continue;
+            };
+
+            match mouse.kind {
+                MouseEventKind::ScrollUp => tabs[i].wheel_up(),
+                MouseEventKind::ScrollDown => tabs[i].wheel_down(),
+                MouseEventKind::Down(MouseButton::Left) => {
+                    let now = Instant::now();
+                    let double = last_click.is_some_and(|(at, c, r)| {
+                        now - at < DOUBLE_CLICK && (c, r) == (column, row)
+                    });
+                    last_click = (!double).then_some((now, column, row));
+
+                    active = i;
+                    tabs[i].click(column, row, double);
+                }
+                _ => (),
+            }
+            continue;
+        }
+
+        if let Event::Key(key) = event
             && key.kind == KeyEventKind::Press
         {
-            match key.code {
-                KeyCode::Char('q') => {
-                    break Ok(());
+            let tab = &mut tabs[active];
+
+            // Ctrl-C quits from anywhere, asking first about unsaved changes unless pressed again
+            if is_interrupt(key) {
+                if dirty && !confirming_quit {
+                    confirming_quit = true;
+                    continue;
                 }
-                KeyCode::Char('h') => {
-                    tree.toggle_current_node_highlight();
+                break;
+            }
+
+            if let Some(running) = pipe.take_if(|_| key.code == KeyCode::Esc) {
+                running.kill();
+                status = "command stopped".to_string();
+                continue;
+            }
+            if pipe.is_some() {
+                continue;
+            }
+
+            if details.is_some() {
+                details = None;
+                continue;
+            }
+
+            // any key closes the help, except the arrows scrolling it when it is cut
+            if let Some(scroll) = &mut help {
+                match key.code {
+                    KeyCode::Up if scroll.is_scrollable() => scroll.up(),
+                    KeyCode::Down if scroll.is_scrollable() => scroll.down(),
+                    _ => help = None,
+                }
+                continue;
+            }
+
+            if let Some((_, _, scroll)) = &mut piped {
+                match key.code {
+                    KeyCode::Up if scroll.is_scrollable() => scroll.up(),
+                    KeyCode::Down if scroll.is_scrollable() => scroll.down(),
+                    _ => piped = None,
+                }
+                continue;
+            }
+
+            if confirming_delete.take().is_some() {
+                if key.code == KeyCode::Char('y') {
+                    delete_selected(tab, &mut status);
+                }
+                continue;
+            }
+
+            if let Some((text, _)) = invalid_edit.take() {
+                if key.code == KeyCode::Char('y') {
+                    status = edit_externally(&mut terminal, tab, &text, &mut invalid_edit);
+                } else {
+                    status = "edit dropped".to_string();
                 }
-                KeyCode::Up => {
-                    tree.next_node_up();
+                continue;
+            }
 
-                    if current_line < up_clamp {
-                        let diff = up_clamp.saturating_sub(current_line) as u16;
+            if confirming_quit {
+                match key.code {
+                    KeyCode::Char('y') => {
+                        confirming_quit = false;
+                        let saved = tabs
+                            .iter_mut()
+                            .filter(|tab| tab.tree.is_dirty())
+                            .try_for_each(|tab| save(tab, options.indent).map(drop));
+                        match saved {
+                            Ok(()) => break,
+                            Err(e) => status = e,
+                        }
+                    }
+                    KeyCode::Char('n') => break,
+                    KeyCode::Char('c') | KeyCode::Esc => confirming_quit = false,
+                    _ => (),
+                }
+                continue;
+            }
 
-                        scroll_y = scroll_y.saturating_sub(diff);
+            if let Some(p) = &mut prompt {
+                match key.code {
+                    KeyCode::Esc => prompt = None,
+                    KeyCode::Tab if p.kind == PromptKind::Search => {
+                        tab.search.toggle_rendered_only()
                     }
+                    KeyCode::Backspace => {
+                        p.input.pop();
+                    }
+                    KeyCode::Char(c) => p.input.push(c),
+                    KeyCode::Enter => match p.kind {
+                        PromptKind::Search => {
+                            tab.search.query = std::mem::take(&mut p.input);
+                            prompt = None;
+
+                            tab.results.reset();
+                            if let Some(k) = tab.search.run(&mut tab.tree) {
+                                tab.tree.select(k);
+                                tab.follow = true;
+                            }
+                            status = tab.search.status();
+                        }
+                        // the prompt stays open on invalid input so it can be corrected
+                        PromptKind::Edit => match serde_json::from_str::<Value>(&p.input) {
+                            Err(e) => p.error = Some(e.to_string()),
+                            Ok(Value::Array(_) | Value::Object(_)) => {
+                                p.error = Some("only a single value can be entered".to_string());
+                            }
+                            // a change of type is only made once confirmed by submitting again
+                            Ok(v)
+                                if p.warned.as_ref() != Some(&p.input)
+                                    && tab
+                                        .tree
+                                        .current_terminal()
+                                        .is_some_and(|old| value_type(old) != value_type(&v)) =>
+                            {
+                                let old = tab.tree.current_terminal().map_or("", value_type);
+                                p.error = Some(format!(
+                                    "changes the {old} to a {}, <Enter> again to confirm",
+                                    value_type(&v)
+                                ));
+                                p.warned = Some(p.input.clone());
+                            }
+                            Ok(v) => {
+                                tab.tree.set_current_terminal(v);
+                                prompt = None;
+                                status = "value updated".to_string();
+                            }
+                        },
+                        PromptKind::Pipe => {
+                            let command = std::mem::take(&mut p.input);
+                            prompt = None;
+                            let json = tab.tree.to_json(tab.tree.current_key(), Indent::default());
+                            match Pipe::spawn(command.clone(), json) {
+                                Ok(running) => {
+                                    status = format!("running {command}… (<Esc> to stop)");
+                                    pipe = Some(running);
+                                }
+                                Err(e) => status = format!("{command}: {e}"),
+                            }
+                        }
+                        PromptKind::Add => {
+                            let entry = if tab.tree.inserts_into_object() == Some(true) {
+                                parse_entry(&p.input).map(|(name, v)| (Some(name), v))
+                            } else {
+                                serde_json::from_str(&p.input)
+                                    .map(|v| (None, v))
+                                    .map_err(|e| e.to_string())
+                            };
+                            match entry.and_then(|(name, v)| {
+                                tab.tree.insert(name, v).map_err(|e| e.to_string())
+                            }) {
+                                Err(e) => p.error = Some(e),
+                                Ok(_) => {
+                                    prompt = None;
+                                    tab.follow = true;
+                                    status = "node added".to_string();
+                                }
+                            }
+                        }
+                        PromptKind::Rename => match tab.tree.rename_current(p.input.clone()) {
+                            Err(e) => p.error = Some(e.to_string()),
+                            Ok(()) => {
+                                prompt = None;
+                                status = "key renamed".to_string();
+                            }
+                        },
+                        PromptKind::Command => match p.input.parse() {
+                            Err(e) => p.error = Some(e),
+                            Ok(Command::Write {
+                                path,
+                                selection,
+                                force,
+                            }) => {
+                                let key = if selection {
+                                    tab.tree.current_key()
+                                } else {
+                                    tab.tree.root()
+                                };
+                                let json = command::to_json(&tab.tree, key, options.indent);
+                                let written = command::write_json(Path::new(&path), &json, force);
+                                match written {
+                                    Ok(bytes) => {
+                                        prompt = None;
+                                        status = format!("wrote {bytes} bytes to {path}");
+                                    }
+                                    Err(e) => p.error = Some(e.to_string()),
+                                }
+                            }
+                            Ok(Command::Save) => match save(tab, options.indent) {
+                                Ok(written) => {
+                                    prompt = None;
+                                    status = written;
+                                }
+                                Err(e) => p.error = Some(e),
+                            },
+                        },
+                        PromptKind::GoTo => match parse_path(&p.input) {
+                            None => p.error = Some("invalid path".to_string()),
+                            Some(path) => {
+                                prompt = None;
+                                match tab.tree.find_path(&path) {
+                                    Some(k) => {
+                                        tab.tree.select(k);
+                                        tab.follow = true;
+                                        status.clear();
+                                    }
+                                    None => status = "path not found".to_string(),
+                                }
+                            }
+                        },
+                    },
+                    _ => (),
                 }
-                KeyCode::Down => {
-                    tree.next_node_down();
+                continue;
+            }
 
-                    if current_line > bot_clamp {
-                        let diff = current_line.saturating_sub(bot_clamp) as u16;
-                        scroll_y += diff;
+            // the raw input has no nodes, it can only be scrolled
+            if tab.is_raw() {
+                match key.code {
+                    KeyCode::Char('q') if dirty => confirming_quit = true,
+                    KeyCode::Char('q') => break,
+                    KeyCode::Char('R') | KeyCode::Esc => tab.toggle_raw(),
+                    KeyCode::Up => tab.scroll_raw_up(),
+                    KeyCode::Down => tab.scroll_raw_down(),
+                    KeyCode::Left => tab.pan_left(),
+                    KeyCode::Right => tab.pan_right(),
+                    _ => (),
+                }
+                continue;
+            }
 
-                        if scroll_y > scroll_y_max {
-                            scroll_y = scroll_y_max;
+            if tab.results.focused {
+                match key.code {
+                    KeyCode::Char('q') if dirty => confirming_quit = true,
+                    KeyCode::Char('q') => break,
+                    KeyCode::Char('L') => tab.results.toggle(),
+                    KeyCode::Esc => tab.results.focused = false,
+                    KeyCode::Up => tab.results.select_previous(),
+                    KeyCode::Down => tab.results.select_next(tab.search.matches().len()),
+                    KeyCode::Enter => {
+                        if let Some(k) = tab.results.selected().and_then(|i| tab.search.select(i)) {
+                            tab.tree.select(k);
+                            tab.follow = true;
+                            status = tab.search.status();
                         }
                     }
+                    _ => (),
                 }
-                KeyCode::Enter => {
-                    tree.toggle_current_node_visibility();
+                continue;
+            }
+
+            let Some(action) = config.keymap.action(key) else {
+                continue;
+            };
+            // a file that failed to parse has no tree to act on, only its input to show
+            if tab.error.is_some()
+                && !matches!(
+                    action,
+                    Action::ToggleRaw
+                        | Action::NextTab
+                        | Action::PreviousTab
+                        | Action::SelectTab
+                        | Action::Help
+                        | Action::Quit
+                )
+            {
+                status = "this file could not be parsed".to_string();
+                continue;
+            }
+            let last_copied_path = copied_path.take();
+
+            match action {
+                Action::Quit if dirty => {
+                    confirming_quit = true;
+                }
+                Action::Quit => {
+                    break;
+                }
+                Action::Help => help = Some(PopupScroll::default()),
+                Action::Pick => {
+                    picked = Some(if options.print_path {
+                        config.path_style.format(&tab.tree.current_path())
+                    } else {
+                        copied_text(&tab.tree)
+                    });
+                    break;
+                }
+                Action::ToggleHighlight => {
+                    tab.tree.toggle_current_node_highlight();
+                }
+                Action::ToggleCompact => {
+                    tab.tree.toggle_compact();
+                    tab.follow = true;
+                }
+                Action::ToggleSortKeys => {
+                    tab.tree.toggle_sort_keys();
+                    tab.follow = true;
+                }
+                Action::Search => {
+                    prompt = Some(Prompt::new(PromptKind::Search, String::new()));
+                }
+                Action::GoTo => {
+                    prompt = Some(Prompt::new(PromptKind::GoTo, String::new()));
+                }
+                Action::Pipe => {
+                    prompt = Some(Prompt::new(PromptKind::Pipe, String::new()));
+                }
+                Action::Command => {
+                    prompt = Some(Prompt::new(PromptKind::Command, String::new()));
+                }
+                Action::EditExternally => {
+                    let text = command::to_json(&tab.tree, tab.tree.current_key(), options.indent);
+                    status = edit_externally(&mut terminal, tab, &text, &mut invalid_edit);
+                }
+                Action::Undo => {
+                    if tab.tree.undo() {
+                        tab.search.forget_deleted(&tab.tree);
+                        tab.follow = true;
+                        status = "undone".to_string();
+                    } else {
+                        status = "nothing to undo".to_string();
+                    }
+                }
+                Action::Redo => {
+                    if tab.tree.redo() {
+                        tab.search.forget_deleted(&tab.tree);
+                        tab.follow = true;
+                        status = "redone".to_string();
+                    } else {
+                        status = "nothing to redo".to_string();
+                    }
+                }
+                Action::Add => match tab.tree.inserts_into_object() {
+                    Some(_) => prompt = Some(Prompt::new(PromptKind::Add, String::new())),
+                    None => status = EditError::NoContainer.to_string(),
+                },
+                Action::Rename => match tab.tree.key_name(tab.tree.current_key()) {
+                    Some(name) => {
+                        prompt = Some(Prompt::new(PromptKind::Rename, name.to_string()));
+                    }
+                    None => status = EditError::NotAnEntry.to_string(),
+                },
+                Action::SearchKey => {
+                    if tab.search.run_current_key(&mut tab.tree) {
+                        tab.results.reset();
+                        status = tab.search.status();
+                    } else {
+                        status = "selected node is not an object entry".to_string();
+                    }
+                }
+                Action::ToggleResults => tab.results.toggle(),
+                Action::Edit => match tab.tree.current_terminal() {
+                    Some(_) => {
+                        let text = tab.tree.to_json(tab.tree.current_key(), Indent::default());
+                        prompt = Some(Prompt::new(PromptKind::Edit, text));
+                    }
+                    None => status = "only values can be edited".to_string(),
+                },
+                Action::Details => {
+                    details = Some(details_text(tab));
+                }
+                Action::Bookmark => {
+                    status = if tab.tree.toggle_bookmark() {
+                        "bookmarked".to_string()
+                    } else {
+                        "bookmark removed".to_string()
+                    };
+                }
+                Action::NextBookmark => match tab.tree.next_bookmark() {
+                    Some(k) => {
+                        tab.tree.select(k);
+                        tab.follow = true;
+                    }
+                    None => status = "no bookmarks".to_string(),
+                },
+                Action::Copy => {
+                    let text = copied_text(&tab.tree);
+                    status = match clipboard.copy(&text) {
+                        Ok(()) if text.len() == 1 => "copied 1 byte".to_string(),
+                        Ok(()) => format!("copied {} bytes", text.len()),
+                        Err(e) => format!("could not copy: {e}"),
+                    };
+                }
+                Action::CopyPath => {
+                    let key = tab.tree.current_key();
+                    let style = match last_copied_path {
+                        Some((k, style)) if k == key => style.next(),
+                        _ => config.path_style,
+                    };
+                    let path = style.format(&tab.tree.current_path());
+                    status = match clipboard.copy(&path) {
+                        Ok(()) => format!("copied {}: {path}", style.name()),
+                        Err(e) => format!("could not copy: {e}"),
+                    };
+                    copied_path = Some((key, style));
+                }
+                Action::CopyGron => {
+                    let gron = tab.tree.to_gron(tab.tree.current_key());
+                    let lines = gron.lines().count();
+                    status = match clipboard.copy(&gron) {
+                        Ok(()) if lines == 1 => "copied 1 line of gron".to_string(),
+                        Ok(()) => format!("copied {lines} lines of gron"),
+                        Err(e) => format!("could not copy: {e}"),
+                    };
+                }
+                Action::ToggleGuides => tab.tree.toggle_indent_guides(),
+                Action::ToggleTimestamps => tab.tree.toggle_timestamps(),
+                Action::ToggleGroupDigits => tab.tree.toggle_group_digits(),
+                Action::ToggleMultiline => {
+                    tab.tree.toggle_multiline_strings();
+                    tab.follow = true;
+                }
+                Action::ToggleRaw => tab.toggle_raw(),
+                Action::ToggleWrap => {
+                    wrap = !wrap;
+                    tab.reset_pan();
+                    tab.follow = true;
+                }
+                Action::PanLeft => tab.pan_left(),
+                Action::PanRight => tab.pan_right(),
+                Action::NextTab => active = (active + 1) % tab_count,
+                Action::PreviousTab => active = (active + tab_count - 1) % tab_count,
+                Action::SelectTab => {
+                    if let KeyCode::Char(c @ '1'..='9') = key.code
+                        && (c as usize - '1' as usize) < tab_count
+                    {
+                        active = c as usize - '1' as usize;
+                    }
+                }
+                Action::Delete => {
+                    let key = tab.tree.current_key();
+                    let children = tab.tree.child_count(key);
+                    if children > DELETE_CONFIRM_CHILDREN && key != tab.tree.root() {
+                        confirming_delete = Some(children);
+                    } else {
+                        delete_selected(tab, &mut status);
+                    }
+                }
+                Action::NextMatch => {
+                    if let Some(k) = tab.search.next() {
+                        tab.tree.select(k);
+                        tab.follow = true;
+                        status = tab.search.status();
+                    }
+                }
+                Action::PreviousMatch => {
+                    if let Some(k) = tab.search.previous() {
+                        tab.tree.select(k);
+                        tab.follow = true;
+                        status = tab.search.status();
+                    }
+                }
+                Action::MoveUp => tab.move_up(),
+                Action::MoveDown => tab.move_down(),
+                Action::Center => tab.center(),
+                Action::ToggleVisibility => {
+                    tab.tree.toggle_current_node_visibility();
                 }
-                _ => (),
             }
         }
     }
+
+    if let Some(running) = pipe {
+        running.kill();
+    }
+
+    for tab in &tabs {
+        if let Some(file) = &tab.session_file {
+            session::save(file, &Session::capture(&tab.tree))?;
+        }
+    }
+
+    Ok(picked)
+}
+
+/// Opens `text` in the user's editor and puts the value it is changed to in place of the
+/// selection, returning the status to show. Text that is not valid JSON is kept in `invalid`
+/// with its error, to be offered for editing again.
+fn edit_externally(
+    terminal: &mut Screen,
+    tab: &mut Tab,
+    text: &str,
+    invalid: &mut Option<(String, String)>,
+) -> String {
+    let edited = match editor::edit(terminal, text) {
+        Ok(edited) => edited,
+        Err(e) => return format!("could not edit: {e}"),
+    };
+
+    match serde_json::from_str::<Value>(&edited) {
+        Err(e) => {
+            *invalid = Some((edited, e.to_string()));
+            String::new()
+        }
+        Ok(value) if value == tab.tree.to_value(tab.tree.current_key()) => "no changes".to_string(),
+        Ok(value) => {
+            tab.tree.replace_current(value);
+            tab.search.forget_deleted(&tab.tree);
+            tab.follow = true;
+            "node replaced".to_string()
+        }
+    }
+}
+
+/// Writes the document of `tab` back to its file, returning the status to show.
+fn save(tab: &mut Tab, indent: Indent) -> Result<String, String> {
+    let Some(path) = &tab.path else {
+        return Err("no filename, use :w <path>".to_string());
+    };
+
+    let json = command::to_json(&tab.tree, tab.tree.root(), indent);
+    match command::save_json(path, &json) {
+        Ok(bytes) => {
+            tab.tree.mark_clean();
+            Ok(format!("wrote {bytes} bytes to {}", path.display()))
+        }
+        Err(e) => Err(format!("could not save {}: {e}", path.display())),
+    }
+}
+
+/// Deletes the selected node of `tab`, reporting in `status` when it cannot be.
+fn delete_selected(tab: &mut Tab, status: &mut String) {
+    if tab.tree.delete_current() {
+        tab.search.forget_deleted(&tab.tree);
+        tab.follow = true;
+    } else {
+        *status = "the root cannot be deleted".to_string();
+    }
+}
+
+/// Whether `key` is Ctrl-C, which raw mode delivers as a key rather than a signal.
+fn is_interrupt(key: KeyEvent) -> bool {
+    key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL)
+}
+
+fn calculate_layout(area: Rect) -> (Rect, Rect, Rect) {
+    let main_layout = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Min(0),
+        Constraint::Length(1),
+    ]);
+    let [title_area, main_area, status_area] = main_layout.areas(area);
+    (title_area, main_area, status_area)
+}
+
+fn details_text(tab: &Tab) -> Text<'static> {
+    let tree = &tab.tree;
+    let mut lines = vec![Line::from(vec![
+        "path: ".bold(),
+        format_path(&tree.current_path()).into(),
+    ])];
+
+    if let Some((line, column, span)) = tab.source_position() {
+        lines.push(Line::from(vec![
+            "source: ".bold(),
+            format!(
+                "line {line}, column {column}, bytes {}..{}",
+                span.start, span.end
+            )
+            .into(),
+        ]));
+    }
+
+    for (label, value) in tree.details(tree.current_key()) {
+        lines.push(Line::from(vec![format!("{label}: ").bold(), value.into()]));
+    }
+
+    // the root stands for the whole document
+    if tree.current_key() == tree.root() {
+        let stats = tree.stats();
+        let counts = [
+            ("objects", stats.objects),
+            ("arrays", stats.arrays),
+            ("strings", stats.strings),
+            ("numbers", stats.numbers),
+            ("booleans", stats.booleans),
+            ("nulls", stats.nulls),
+            ("nodes", stats.nodes()),
+            ("depth", stats.max_depth),
+        ];
+        lines.push(Line::default());
+        for (label, count) in counts {
+            lines.push(Line::from(vec![
+                format!("{label}: ").bold(),
+                count.to_string().into(),
+            ]));
+        }
+    }
+
+    lines.into()
 }
 
-fn calculate_layout(area: Rect) -> (Rect, Rect) {
-    let main_layout = Layout::vertical([Constraint::Length(1), Constraint::Min(0)]);
-    let [title_area, main_area] = main_layout.areas(area);
-    (title_area, main_area)
+/// The raw text of the selected scalar, strings without their quotes, or the pretty-printed
+/// JSON of the selected container, collapsed children included.
+fn copied_text(tree: &Tree) -> String {
+    match tree.current_terminal() {
+        Some(Value::String(s)) => s.clone(),
+        _ => tree.to_json(tree.current_key(), Indent::default()),
+    }
 }
 
-fn render_title(frame: &mut Frame, area: Rect, title: &str) {
+fn split_results(area: Rect) -> (Rect, Rect) {
+    let layout = Layout::horizontal([Constraint::Ratio(2, 3), Constraint::Ratio(1, 3)]);
+    let [tree_area, results_area] = layout.areas(area);
+    (tree_area, results_area)
+}
+
+fn render_title(frame: &mut Frame, area: Rect, title: &str, theme: &Theme) {
     frame.render_widget(
         Paragraph::new(title)
-            .dark_gray()
+            .style(theme.muted_style())
             .alignment(Alignment::Center),
         area,
     );
 }
+
+fn render_status(frame: &mut Frame, area: Rect, status: &str, theme: &Theme) {
+    frame.render_widget(Paragraph::new(status).style(theme.muted_style()), area);
+}
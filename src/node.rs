@@ -1,24 +1,193 @@
+use std::{cell::RefCell, collections::HashSet, ops::Range, sync::Arc};
+
 use ratatui::{
-    prelude::Stylize,
+    style::Style,
     text::{Line, Span, Text},
 };
-use serde_json::Value;
-use slotmap::{DefaultKey, SlotMap};
+use serde::Deserialize;
+use serde_json::{Number, Value};
+use slotmap::{DefaultKey, SecondaryMap, SlotMap};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::{
+    diff::DiffStatus,
+    error::{EditError, TreeError},
+    history::{Change, History},
+    path::{PathSegment, format_gron_path},
+    source,
+    theme::Theme,
+    timestamp,
+};
 
 #[derive(Debug)]
+/// A JSON document whose containers can be collapsed, with one selected node.
 pub struct Tree {
     root: DefaultKey,
     slot_map: SlotMap<DefaultKey, Node>,
     current_node: DefaultKey,
+    compact: bool,
+    sort_keys: bool,
+    theme: Theme,
+    collapsed_preview: bool,
+    view_width: usize,
+    indent_guides: bool,
+    indent: Indent,
+    quotes: QuoteStyle,
+    string_limit: usize,
+    multiline_strings: bool,
+    group_digits: bool,
+    timestamps: bool,
+    /// Whether the selection is shown, see [`Tree::selected_line_range`].
+    highlight: bool,
+    /// Moves the selection to the first child of a container when expanding it.
+    select_expanded: bool,
+    /// Collapses the containers the selection walks out of, see [`Tree::set_focus_mode`].
+    focus_mode: bool,
+    bookmarks: HashSet<DefaultKey>,
+    history: History,
+    /// Output of the last [`Tree::to_text`], dropped by anything changing how the tree renders.
+    rendered: RefCell<Option<Arc<Text<'static>>>>,
+    /// [`Tree::line_count`] of every node, dropped along with the rendered text.
+    line_counts: RefCell<Option<SecondaryMap<DefaultKey, usize>>>,
+    /// The text the document was read from, kept while containers are left to build so that the
+    /// spans of their children can be recorded once they are.
+    source: Option<Arc<str>>,
+}
+
+/// Indentation of one nesting level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Indent {
+    Spaces(usize),
+    /// Drawn as [`Indent::TAB_WIDTH`] columns so that the layout stays stable on screen.
+    Tab,
+}
+
+impl Default for Indent {
+    fn default() -> Self {
+        Indent::Spaces(2)
+    }
+}
+
+impl Indent {
+    pub const TAB_WIDTH: usize = 4;
+
+    /// Number of columns taken on screen by one level.
+    pub fn width(&self) -> usize {
+        match self {
+            Indent::Spaces(n) => *n,
+            Indent::Tab => Self::TAB_WIDTH,
+        }
+    }
+
+    /// Text of one level when written out, as opposed to drawn.
+    pub fn unit(&self) -> String {
+        match self {
+            Indent::Spaces(n) => " ".repeat(*n),
+            Indent::Tab => "\t".to_string(),
+        }
+    }
+}
+
+/// How strings and keys are quoted on screen, escaped as in JSON either way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QuoteStyle {
+    #[default]
+    Double,
+    Single,
+    /// Without quotes, as YAML mostly writes strings.
+    Bare,
+}
+
+impl QuoteStyle {
+    fn quote(self) -> &'static str {
+        match self {
+            QuoteStyle::Double => "\"",
+            QuoteStyle::Single => "'",
+            QuoteStyle::Bare => "",
+        }
+    }
+
+    /// Escapes `s` as JSON does, so that control characters never break a rendered line, with
+    /// the quote of this style escaped in place of `"`.
+    fn escape(self, s: &str) -> String {
+        // serializing a str cannot fail
+        let quoted = serde_json::to_string(s).unwrap();
+        let escaped = &quoted[1..quoted.len() - 1];
+        match self {
+            QuoteStyle::Double => escaped.to_string(),
+            // a `"` always follows the `\` escaping it, a literal backslash being doubled
+            QuoteStyle::Single => escaped.replace("\\\"", "\"").replace('\'', "\\'"),
+            QuoteStyle::Bare => escaped.replace("\\\"", "\""),
+        }
+    }
+
+    fn quoted(self, s: &str) -> String {
+        let quote = self.quote();
+        format!("{quote}{}{quote}", self.escape(s))
+    }
 }
 
 #[derive(Debug)]
+/// A value of the document, identified in its [`Tree`] by a [`DefaultKey`].
 pub struct Node {
     parent: Option<DefaultKey>,
-    highlighted: bool,
+    matched: bool,
+    diff: Option<DiffStatus>,
+    /// Edited since the document was last saved.
+    modified: bool,
+    /// Shows a string longer than the tree's limit in full.
+    expanded: bool,
+    /// Where the value was read from, see [`crate::source::record_spans`].
+    span: Option<Range<usize>>,
+    /// The text a number was read from, when serde_json would write it otherwise, as `1e+3`
+    /// for `1e3`.
+    written: Option<Box<str>>,
     node: NodeType,
 }
 
+/// Number of values of each type in a document, see [`Tree::stats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TreeStats {
+    pub objects: usize,
+    pub arrays: usize,
+    pub strings: usize,
+    pub numbers: usize,
+    pub booleans: usize,
+    pub nulls: usize,
+    /// Ancestors of the most deeply nested value, 0 for a lone scalar.
+    pub max_depth: usize,
+}
+
+impl TreeStats {
+    pub fn nodes(&self) -> usize {
+        self.objects + self.arrays + self.strings + self.numbers + self.booleans + self.nulls
+    }
+}
+
+impl std::fmt::Display for TreeStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "objects {} · arrays {} · strings {} · numbers {} · booleans {} · nulls {} · depth {}",
+            self.objects,
+            self.arrays,
+            self.strings,
+            self.numbers,
+            self.booleans,
+            self.nulls,
+            self.max_depth
+        )
+    }
+}
+
+/// Read-only shape of a node, for the modules walking a tree alongside another one.
+pub(crate) enum NodeView<'a> {
+    Terminal(&'a Value),
+    Array(&'a [DefaultKey]),
+    Object(&'a [(String, DefaultKey)]),
+}
+
 #[derive(Debug)]
 enum NodeType {
     Terminal(Value),
@@ -29,6 +198,63 @@ enum NodeType {
 enum NonTerminalNode {
     Array(Vec<DefaultKey>),
     Object(Vec<(String, DefaultKey)>),
+    /// A collapsed container whose children are built from its value once expanded, see
+    /// [`Tree::from_value_to_depth`].
+    Unbuilt(Value),
+}
+
+/// A child of a container, as a node or, until the container is built, as part of its value,
+/// for the walks reading whole documents without building them.
+#[derive(Clone, Copy)]
+enum Child<'a> {
+    Built(DefaultKey),
+    Unbuilt(&'a Value),
+}
+
+/// What those walks need to know of a [`Child`].
+enum Shape<'a> {
+    /// A terminal, with the text a number was read from as kept by `Node::written`.
+    Terminal(&'a Value, Option<&'a str>),
+    Container {
+        array: bool,
+        entries: Entries<'a>,
+    },
+}
+
+/// The children of a container, only listed by the walks going into it.
+#[derive(Clone, Copy)]
+enum Entries<'a> {
+    Built(&'a NonTerminalNode),
+    Unbuilt(&'a Value),
+}
+
+impl<'a> Entries<'a> {
+    /// Children in display order, object entries being sorted by key when `sorted` is set.
+    fn list(self, sorted: bool) -> Vec<(Option<&'a str>, Child<'a>)> {
+        let value = match self {
+            Entries::Built(NonTerminalNode::Unbuilt(value)) | Entries::Unbuilt(value) => value,
+            Entries::Built(node) => {
+                return node
+                    .entries(sorted)
+                    .into_iter()
+                    .map(|(name, key)| (name, Child::Built(key)))
+                    .collect();
+            }
+        };
+
+        let mut entries: Vec<_> = match value {
+            Value::Array(items) => items.iter().map(|v| (None, Child::Unbuilt(v))).collect(),
+            Value::Object(map) => map
+                .iter()
+                .map(|(name, v)| (Some(name.as_str()), Child::Unbuilt(v)))
+                .collect(),
+            _ => unreachable!("only containers have entries"),
+        };
+        if sorted {
+            entries.sort_by_key(|(name, _)| *name);
+        }
+        entries
+    }
 }
 
 #[derive(Debug)]
@@ -44,6 +270,28 @@ impl Node {
             NodeType::NonTerminal(v) => v.is_visible(),
         }
     }
+
+    /// How the node is coloured: as it differs from the compared document, else as changed
+    /// once edited.
+    fn status(&self) -> Option<DiffStatus> {
+        self.diff.or(self.modified.then_some(DiffStatus::Changed))
+    }
+
+    /// A terminal written as JSON, numbers as they were read.
+    fn terminal_json(&self) -> String {
+        match &self.node {
+            NodeType::Terminal(v) => terminal_json(v, self.written.as_deref()),
+            NodeType::NonTerminal(_) => unreachable!("only terminals are written on their own"),
+        }
+    }
+
+    /// A terminal as shown, strings quoted with `quotes`.
+    fn terminal_text(&self, quotes: QuoteStyle) -> String {
+        match &self.node {
+            NodeType::Terminal(Value::String(s)) => quotes.quoted(s),
+            _ => self.terminal_json(),
+        }
+    }
 }
 
 impl HidableValue {
@@ -56,99 +304,353 @@ impl HidableValue {
     }
 
     pub fn is_array(&self) -> bool {
-        match self.node {
+        match &self.node {
             NonTerminalNode::Array(_) => true,
             NonTerminalNode::Object(_) => false,
+            NonTerminalNode::Unbuilt(value) => value.is_array(),
+        }
+    }
+
+    pub fn find_last(&self, sorted: bool) -> Option<DefaultKey> {
+        self.node.find_last(sorted)
+    }
+
+    pub fn brackets(&self) -> (&'static str, &'static str) {
+        brackets(self.is_array())
+    }
+
+    /// Number of direct children, e.g. `42 items` or `1 key`.
+    pub fn size_label(&self) -> String {
+        let count = self.node.len();
+        let unit = if self.is_array() { "item" } else { "key" };
+
+        if count == 1 {
+            format!("{count} {unit}")
+        } else {
+            format!("{count} {unit}s")
         }
     }
 
-    pub fn find_last(&self) -> Option<DefaultKey> {
-        self.node.find_last()
+    /// How the container reads once collapsed, e.g. `[… 42 items]`.
+    pub fn summary(&self) -> String {
+        let (open, close) = self.brackets();
+        format!("{open}… {}{close}", self.size_label())
     }
 }
 
 impl NonTerminalNode {
-    pub fn find_last(&self) -> Option<DefaultKey> {
+    pub fn len(&self) -> usize {
+        match self {
+            NonTerminalNode::Array(arr) => arr.len(),
+            NonTerminalNode::Object(obj) => obj.len(),
+            NonTerminalNode::Unbuilt(Value::Array(arr)) => arr.len(),
+            NonTerminalNode::Unbuilt(Value::Object(obj)) => obj.len(),
+            NonTerminalNode::Unbuilt(_) => unreachable!("only containers are left unbuilt"),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Children in display order, object entries being sorted by key when `sorted` is set. A
+    /// container not built yet has none to give.
+    pub fn entries(&self, sorted: bool) -> Vec<(Option<&str>, DefaultKey)> {
         match self {
-            NonTerminalNode::Array(arr) => arr.last().copied(),
+            NonTerminalNode::Array(arr) => arr.iter().map(|k| (None, *k)).collect(),
             NonTerminalNode::Object(obj) => {
-                let (_, k) = obj.last().unwrap();
-                Some(k).copied()
+                let mut entries: Vec<_> = obj.iter().map(|(n, k)| (Some(n.as_str()), *k)).collect();
+                if sorted {
+                    entries.sort_by_key(|(name, _)| *name);
+                }
+                entries
             }
+            NonTerminalNode::Unbuilt(_) => vec![],
         }
     }
 
-    pub fn find_next_key(&self, key: DefaultKey) -> Option<DefaultKey> {
-        match &self {
-            Self::Array(array) => array
-                .iter()
-                .position(|k| *k == key)
-                .and_then(|i| {
-                    if i < (array.len() - 1) {
-                        array.get(i + 1)
-                    } else {
-                        None
-                    }
-                })
-                .copied(),
-            Self::Object(obj) => obj
-                .iter()
-                .position(|(_, k)| *k == key)
-                .and_then(|i| {
-                    if i < (obj.len() - 1) {
-                        let o = obj.get(i + 1);
-                        let (_, k) = o.unwrap();
-                        Some(k)
-                    } else {
-                        None
-                    }
-                })
-                .copied(),
-        }
+    pub fn children(&self, sorted: bool) -> Vec<DefaultKey> {
+        self.entries(sorted).into_iter().map(|(_, k)| k).collect()
     }
 
-    pub fn find_previous_key(&self, key: DefaultKey) -> Option<DefaultKey> {
-        match &self {
-            Self::Array(array) => array
-                .iter()
-                .position(|k| *k == key)
-                //.and_then(|i| i > 0 { array.get(i - 1) } else { None })
-                .and_then(|i| if i > 0 { array.get(i - 1) } else { None })
-                .copied(),
-            Self::Object(obj) => obj
-                .iter()
-                .position(|(_, k)| *k == key)
-                .and_then(|i| {
-                    if i > 0 {
-                        let o = obj.get(i - 1);
-                        let (_, k) = o.unwrap();
-                        Some(k)
-                    } else {
-                        None
-                    }
-                })
-                .copied(),
-        }
+    pub fn find_last(&self, sorted: bool) -> Option<DefaultKey> {
+        self.children(sorted).last().copied()
+    }
+
+    pub fn find_next_key(&self, key: DefaultKey, sorted: bool) -> Option<DefaultKey> {
+        let children = self.children(sorted);
+        children
+            .iter()
+            .position(|k| *k == key)
+            .and_then(|i| children.get(i + 1))
+            .copied()
+    }
+
+    pub fn find_previous_key(&self, key: DefaultKey, sorted: bool) -> Option<DefaultKey> {
+        let children = self.children(sorted);
+        children
+            .iter()
+            .position(|k| *k == key)
+            .and_then(|i| i.checked_sub(1))
+            .and_then(|i| children.get(i))
+            .copied()
     }
 }
 
 impl Tree {
-    const INDENT: &str = "  ";
+    const COMPACT_WIDTH: usize = 60;
+    const STRING_LIMIT: usize = 200;
 
+    /// Returns the node stored under `key`, panicking if it does not belong to this tree.
     pub fn key_to_node(&self, key: DefaultKey) -> &Node {
         self.slot_map.get(key).unwrap()
     }
 
+    /// Mutable counterpart of [`Tree::key_to_node`].
     pub fn key_to_node_mut(&mut self, key: DefaultKey) -> &mut Node {
+        self.invalidate();
         self.slot_map.get_mut(key).unwrap()
     }
 
-    pub fn next_node_down(&mut self) -> Option<DefaultKey> {
+    /// Drops the cached text, to be rendered again on the next [`Tree::to_text`].
+    fn invalidate(&mut self) {
+        self.rendered.get_mut().take();
+        self.line_counts.get_mut().take();
+    }
+
+    /// Returns the key of the document's root, whose [`Tree::to_value`] is the whole document.
+    pub fn root(&self) -> DefaultKey {
+        self.root
+    }
+
+    /// The shape of `key`, a container not built yet showing no children.
+    pub(crate) fn view(&self, key: DefaultKey) -> NodeView<'_> {
+        match &self.key_to_node(key).node {
+            NodeType::Terminal(v) => NodeView::Terminal(v),
+            NodeType::NonTerminal(v) => match &v.node {
+                NonTerminalNode::Array(arr) => NodeView::Array(arr),
+                NonTerminalNode::Object(obj) => NodeView::Object(obj),
+                NonTerminalNode::Unbuilt(Value::Array(_)) => NodeView::Array(&[]),
+                NonTerminalNode::Unbuilt(_) => NodeView::Object(&[]),
+            },
+        }
+    }
+
+    /// Whether `key` is a container whose children are not built yet.
+    pub(crate) fn is_unbuilt(&self, key: DefaultKey) -> bool {
+        matches!(
+            &self.key_to_node(key).node,
+            NodeType::NonTerminal(HidableValue {
+                node: NonTerminalNode::Unbuilt(_),
+                ..
+            })
+        )
+    }
+
+    /// Builds the children of `key` when it was left unbuilt, each container among them being
+    /// left collapsed and unbuilt in turn, and records their spans in the text kept for it.
+    pub(crate) fn build(&mut self, key: DefaultKey) {
+        if self.build_children(key)
+            && let (Some(text), Some(span)) = (self.source.clone(), self.span(key))
         {
-            let current_node = self.key_to_node_mut(self.current_node);
-            current_node.highlighted = false;
+            source::record_spans_within(self, key, &text, span);
+        }
+    }
+
+    /// Builds every container left unbuilt, as searching the whole document needs.
+    pub(crate) fn build_all(&mut self) {
+        // the outermost ones are built first, then their spans recorded once for each
+        let mut outermost = vec![];
+        let mut stack = vec![self.root];
+        while let Some(key) = stack.pop() {
+            if self.is_unbuilt(key) {
+                outermost.push(key);
+            } else if let NodeType::NonTerminal(v) = &self.key_to_node(key).node {
+                stack.extend(v.node.children(false));
+            }
+        }
+
+        for &key in &outermost {
+            let mut stack = vec![key];
+            while let Some(key) = stack.pop() {
+                self.build_children(key);
+                if let NodeType::NonTerminal(v) = &self.key_to_node(key).node {
+                    stack.extend(v.node.children(false));
+                }
+            }
+        }
+
+        // nothing is left to build, so the text can go
+        if let Some(text) = self.source.take() {
+            for key in outermost {
+                if let Some(span) = self.span(key) {
+                    source::record_spans_within(self, key, &text, span);
+                }
+            }
+        }
+    }
+
+    /// Turns the value of an unbuilt container into its children, returning whether it was one.
+    fn build_children(&mut self, key: DefaultKey) -> bool {
+        let value = match &mut self.slot_map[key].node {
+            NodeType::NonTerminal(HidableValue {
+                node: NonTerminalNode::Unbuilt(value),
+                ..
+            }) => std::mem::take(value),
+            _ => return false,
+        };
+
+        let mut child = |value| self.slot_map.insert(unbuilt_node(value, Some(key)));
+        let node = match value {
+            Value::Array(items) => {
+                NonTerminalNode::Array(items.into_iter().map(&mut child).collect())
+            }
+            Value::Object(map) => NonTerminalNode::Object(
+                map.into_iter()
+                    .map(|(name, value)| (name, child(value)))
+                    .collect(),
+            ),
+            _ => unreachable!("only containers are left unbuilt"),
+        };
+        if let NodeType::NonTerminal(v) = &mut self.key_to_node_mut(key).node {
+            v.node = node;
+        }
+        true
+    }
+
+    /// Keeps `text`, which the tree was read from, to record the spans of the containers built
+    /// later, when any is left to build.
+    pub(crate) fn keep_source(&mut self, text: &str) {
+        let unbuilt = self.slot_map.keys().any(|key| self.is_unbuilt(key));
+        self.source = unbuilt.then(|| text.into());
+    }
+
+    /// What the walks over whole documents need of `child`.
+    fn shape<'a>(&'a self, child: Child<'a>) -> Shape<'a> {
+        let value = match child {
+            Child::Unbuilt(value) => value,
+            Child::Built(key) => {
+                let node = self.key_to_node(key);
+                return match &node.node {
+                    NodeType::Terminal(value) => Shape::Terminal(value, node.written.as_deref()),
+                    NodeType::NonTerminal(v) => Shape::Container {
+                        array: v.is_array(),
+                        entries: Entries::Built(&v.node),
+                    },
+                };
+            }
+        };
+
+        match value {
+            Value::Array(_) | Value::Object(_) => Shape::Container {
+                array: value.is_array(),
+                entries: Entries::Unbuilt(value),
+            },
+            terminal => Shape::Terminal(terminal, None),
+        }
+    }
+
+    /// Flags `key` and its descendants with `status`.
+    pub(crate) fn mark_diff(&mut self, key: DefaultKey, status: DiffStatus) {
+        let mut stack = vec![key];
+
+        while let Some(key) = stack.pop() {
+            let node = self.key_to_node_mut(key);
+            node.diff = Some(status);
+            if let NodeType::NonTerminal(v) = &node.node {
+                stack.extend(v.node.children(false));
+            }
+        }
+    }
+
+    /// How the node differs from the other side of a comparison, see
+    /// [`mark_differences`](crate::diff::mark_differences).
+    pub fn diff_status(&self, key: DefaultKey) -> Option<DiffStatus> {
+        self.key_to_node(key).diff
+    }
+
+    /// Flags `key` and its descendants as edited.
+    fn mark_modified(&mut self, key: DefaultKey) {
+        let mut stack = vec![key];
+
+        while let Some(key) = stack.pop() {
+            let node = self.key_to_node_mut(key);
+            node.modified = true;
+            if let NodeType::NonTerminal(v) = &node.node {
+                stack.extend(v.node.children(false));
+            }
         }
+    }
+
+    /// Whether the node was added, replaced or renamed, or lost children, since the document was
+    /// last saved. Such nodes are drawn as changed in a comparison:
+    ///
+    /// ```
+    /// use json_tui::Tree;
+    /// use serde_json::json;
+    ///
+    /// let mut tree = Tree::try_from_str(r#"{"a": [1, 2], "b": true}"#).unwrap();
+    /// let (a, b) = (tree.node_at_line(1).unwrap(), tree.node_at_line(5).unwrap());
+    /// tree.select(tree.node_at_line(2).unwrap());
+    /// tree.delete_current();
+    /// tree.select(b);
+    /// tree.replace_current(json!(false));
+    /// assert!(tree.is_modified(a) && tree.is_modified(b) && !tree.is_modified(tree.root()));
+    /// tree.mark_clean();
+    /// assert!(!tree.is_modified(a) && !tree.is_modified(b));
+    /// ```
+    pub fn is_modified(&self, key: DefaultKey) -> bool {
+        self.key_to_node(key).modified
+    }
+
+    pub(crate) fn set_span(&mut self, key: DefaultKey, span: Range<usize>) {
+        // leaves the rendered text alone, which does not show it
+        self.slot_map[key].span = Some(span);
+    }
+
+    /// Keeps the text the number `key` was read from, to be shown and written as it was.
+    pub(crate) fn set_written(&mut self, key: DefaultKey, text: &str) {
+        self.key_to_node_mut(key).written = Some(text.into());
+    }
+
+    /// Byte range of the text the value was read from, `None` for a value added or replaced
+    /// since, or for input whose spans were not recorded with
+    /// [`record_spans`](crate::source::record_spans).
+    pub fn span(&self, key: DefaultKey) -> Option<Range<usize>> {
+        self.key_to_node(key).span.clone()
+    }
 
+    /// Moves the selection to the next rendered node, returning it if there was one. Walking
+    /// down from the root visits every node shown, in document order, and walking back up
+    /// retraces the same nodes:
+    ///
+    /// ```
+    /// use json_tui::Tree;
+    ///
+    /// let doc = r#"{"a": [1, {"b": null}], "c": {}, "d": {"e": 1}}"#;
+    /// let mut tree = Tree::try_from_str(doc).unwrap();
+    /// for _ in 0..6 {
+    ///     tree.next_node_down();
+    /// }
+    /// tree.toggle_current_node_visibility();
+    /// while tree.next_node_up().is_some() {}
+    ///
+    /// let mut down = vec![tree.current_key()];
+    /// while let Some(key) = tree.next_node_down() {
+    ///     down.push(key);
+    /// }
+    /// // "e" is hidden in the collapsed "d"
+    /// assert_eq!(down.len(), 7);
+    /// assert!(down.iter().all(|&key| tree.contains(key)));
+    ///
+    /// let mut up = vec![tree.current_key()];
+    /// while let Some(key) = tree.next_node_up() {
+    ///     up.push(key);
+    /// }
+    /// up.reverse();
+    /// assert_eq!(up, down);
+    /// ```
+    pub fn next_node_down(&mut self) -> Option<DefaultKey> {
         let current_node = self.key_to_node(self.current_node);
 
         let next_key = match &current_node.node {
@@ -162,7 +664,9 @@ impl Tree {
                         .parent
                         .and_then(|k| self.slot_map.get(k))
                         .and_then(|n| match &n.node {
-                            NodeType::NonTerminal(v) => v.node.find_next_key(current_key),
+                            NodeType::NonTerminal(v) => {
+                                v.node.find_next_key(current_key, self.sort_keys)
+                            }
                             NodeType::Terminal(_) => unreachable!(),
                         });
 
@@ -176,7 +680,8 @@ impl Tree {
                     };
                 }
             }
-            NodeType::NonTerminal(_) if !current_node.is_visible() => {
+            // collapsed and empty containers have no children to descend into
+            NodeType::NonTerminal(v) if !v.is_visible() || v.node.is_empty() => {
                 let mut current_key = self.current_node;
 
                 loop {
@@ -186,7 +691,9 @@ impl Tree {
                         .parent
                         .and_then(|k| self.slot_map.get(k))
                         .and_then(|n| match &n.node {
-                            NodeType::NonTerminal(v) => v.node.find_next_key(current_key),
+                            NodeType::NonTerminal(v) => {
+                                v.node.find_next_key(current_key, self.sort_keys)
+                            }
                             NodeType::Terminal(_) => unreachable!(),
                         });
 
@@ -202,31 +709,44 @@ impl Tree {
             }
             NodeType::NonTerminal(v) => match &v.node {
                 NonTerminalNode::Array(array) => array.first().cloned(),
-                NonTerminalNode::Object(obj) => {
-                    let (_, k) = obj.first().unwrap();
-                    Some(k).copied()
+                NonTerminalNode::Object(_) => v.node.children(self.sort_keys).first().copied(),
+                NonTerminalNode::Unbuilt(_) => {
+                    unreachable!("containers left unbuilt are collapsed")
                 }
             },
         };
 
         if let Some(k) = next_key {
-            self.current_node = k;
-        }
-
-        {
-            let current_node = self.key_to_node_mut(self.current_node);
-            current_node.highlighted = true;
+            let left = std::mem::replace(&mut self.current_node, k);
+            if self.focus_mode {
+                self.collapse_left(left);
+            }
         }
+        self.highlight = true;
 
         next_key
     }
 
+    /// Moves the selection to the previous rendered node, returning it if there was one. It
+    /// undoes a step of [`Tree::next_node_down`], and neither moves past either end:
+    ///
+    /// ```
+    /// use json_tui::Tree;
+    ///
+    /// let mut tree = Tree::try_from_str(r#"[[1, [2]], {"a": {}}, [], 3]"#).unwrap();
+    /// assert_eq!(tree.next_node_up(), None);
+    ///
+    /// loop {
+    ///     let here = tree.current_key();
+    ///     let Some(next) = tree.next_node_down() else {
+    ///         assert_eq!(tree.current_key(), here);
+    ///         break;
+    ///     };
+    ///     assert_eq!(tree.next_node_up(), Some(here));
+    ///     tree.select(next);
+    /// }
+    /// ```
     pub fn next_node_up(&mut self) -> Option<DefaultKey> {
-        {
-            let current_node = self.key_to_node_mut(self.current_node);
-            current_node.highlighted = false;
-        }
-
         let current_node = self.key_to_node(self.current_node);
 
         let next_key = {
@@ -236,7 +756,9 @@ impl Tree {
                     None => None,
                     Some(n) => match &n.node {
                         NodeType::NonTerminal(_) if !n.is_visible() => Some(k),
-                        NodeType::NonTerminal(v) => v.node.find_previous_key(self.current_node),
+                        NodeType::NonTerminal(v) => {
+                            v.node.find_previous_key(self.current_node, self.sort_keys)
+                        }
                         NodeType::Terminal(_) => unreachable!(),
                     },
                 }
@@ -251,7 +773,10 @@ impl Tree {
                         match &node.node {
                             NodeType::Terminal(_) => break s,
                             NodeType::NonTerminal(_) if !node.is_visible() => break s,
-                            NodeType::NonTerminal(v) => v.find_last(),
+                            NodeType::NonTerminal(v) => match v.find_last(self.sort_keys) {
+                                None => break s,
+                                last => last,
+                            },
                         }
                     }
                     None => break current_node.parent,
@@ -261,284 +786,2580 @@ impl Tree {
         };
 
         if let Some(k) = next_key {
-            self.current_node = k;
-        }
-
-        {
-            let current_node = self.key_to_node_mut(self.current_node);
-            current_node.highlighted = true;
+            let left = std::mem::replace(&mut self.current_node, k);
+            if self.focus_mode {
+                self.collapse_left(left);
+            }
         }
+        self.highlight = true;
 
         next_key
     }
 
+    /// Builds the tree of `v`, with every container expanded and the root selected.
     pub fn from_value(v: Value) -> Self {
         let mut slot_map = SlotMap::new();
         let root_key = value_to_key(v, &mut slot_map, None);
+        Self::with_nodes(slot_map, root_key)
+    }
+
+    /// Builds the tree of `v` as [`Tree::expand_to_depth`] leaves it, with the containers
+    /// `depth` levels deep or more collapsed. Their children are only built once expanded, so
+    /// that the parts of a big document that are never opened cost no more than their value:
+    ///
+    /// ```
+    /// use json_tui::Tree;
+    /// use serde_json::json;
+    ///
+    /// let value = json!({"a": {"b": [1, 2]}, "c": [3]});
+    /// let mut tree = Tree::from_value_to_depth(value.clone(), 1);
+    /// let lines = |tree: &Tree| -> Vec<String> {
+    ///     tree.to_text().lines.iter().map(|l| l.to_string()).collect()
+    /// };
+    /// assert_eq!(lines(&tree), ["{", "│ \"a\": {… 1 key},", "│ \"c\": [… 1 item]", "}"]);
+    /// assert_eq!(tree.to_value(tree.root()), value);
+    ///
+    /// tree.select(tree.node_at_line(1).unwrap());
+    /// tree.toggle_current_node_visibility();
+    /// assert_eq!(lines(&tree)[2], "│ │ \"b\": [… 2 items]");
+    /// ```
+    pub fn from_value_to_depth(v: Value, depth: usize) -> Self {
+        let mut slot_map = SlotMap::new();
+        let root_key = slot_map.insert(unbuilt_node(v, None));
+        let mut tree = Self::with_nodes(slot_map, root_key);
+        tree.expand_to_depth(depth);
+        tree
+    }
 
-        let mut ret = Self {
+    fn with_nodes(slot_map: SlotMap<DefaultKey, Node>, root_key: DefaultKey) -> Self {
+        Self {
             root: root_key,
             slot_map,
             current_node: root_key,
-        };
+            compact: false,
+            sort_keys: false,
+            theme: Theme::default(),
+            collapsed_preview: false,
+            view_width: Self::COMPACT_WIDTH,
+            indent_guides: true,
+            indent: Indent::default(),
+            quotes: QuoteStyle::default(),
+            string_limit: Self::STRING_LIMIT,
+            multiline_strings: false,
+            group_digits: false,
+            timestamps: false,
+            highlight: true,
+            select_expanded: false,
+            focus_mode: false,
+            bookmarks: HashSet::new(),
+            history: History::new(),
+            rendered: RefCell::new(None),
+            line_counts: RefCell::new(None),
+            source: None,
+        }
+    }
 
-        ret.highlight_current_node();
+    /// Rebuilds the value of `key` and its descendants, in document order and whether collapsed
+    /// or not.
+    ///
+    /// ```
+    /// use json_tui::Tree;
+    /// use serde_json::json;
+    ///
+    /// let mut tree = Tree::try_from_str(r#"{"b": [1, {"c": null}], "a": "x"}"#).unwrap();
+    /// tree.toggle_current_node_visibility();
+    /// tree.toggle_sort_keys();
+    /// let root = tree.current_key();
+    /// assert_eq!(tree.to_value(root), json!({"b": [1, {"c": null}], "a": "x"}));
+    /// ```
+    pub fn to_value(&self, key: DefaultKey) -> Value {
+        // children are converted before their parent, which then takes their values off the end
+        let mut stack = vec![(key, false)];
+        let mut values = vec![];
+
+        while let Some((key, children_done)) = stack.pop() {
+            match &self.key_to_node(key).node {
+                NodeType::Terminal(v)
+                | NodeType::NonTerminal(HidableValue {
+                    node: NonTerminalNode::Unbuilt(v),
+                    ..
+                }) => values.push(v.clone()),
+                NodeType::NonTerminal(v) if !children_done => {
+                    stack.push((key, true));
+                    stack.extend(v.node.children(false).into_iter().rev().map(|k| (k, false)));
+                }
+                NodeType::NonTerminal(v) => {
+                    let children = values.split_off(values.len() - v.node.len());
+                    values.push(match &v.node {
+                        NonTerminalNode::Array(_) => Value::Array(children),
+                        NonTerminalNode::Object(obj) => Value::Object(
+                            obj.iter()
+                                .map(|(name, _)| name.clone())
+                                .zip(children)
+                                .collect(),
+                        ),
+                        NonTerminalNode::Unbuilt(_) => unreachable!("taken whole above"),
+                    });
+                }
+            }
+        }
 
-        ret
+        values
+            .pop()
+            .expect("the value of `key` is left once its children are taken")
     }
 
-    pub fn toggle_current_node_visibility(&mut self) {
-        let node = self.slot_map.get_mut(self.current_node).unwrap();
-        match &mut node.node {
-            NodeType::Terminal(_) => (),
-            NodeType::NonTerminal(v) => {
-                v.toggle_visibility();
+    /// Writes the value of `key` as JSON, pretty-printed as serde_json does with `indent`, and
+    /// numbers as they were read. Nothing is left out of collapsed containers:
+    ///
+    /// ```
+    /// use json_tui::{Tree, node::Indent};
+    ///
+    /// let mut tree = Tree::try_from_str(r#"{"a": [1e3, 2.5E-7, -0.0], "b": {}}"#).unwrap();
+    /// tree.toggle_current_node_visibility();
+    /// assert_eq!(
+    ///     tree.to_json(tree.root(), Indent::Spaces(2)),
+    ///     "{\n  \"a\": [\n    1e3,\n    2.5E-7,\n    -0.0\n  ],\n  \"b\": {}\n}"
+    /// );
+    /// ```
+    pub fn to_json(&self, key: DefaultKey, indent: Indent) -> String {
+        enum Piece<'a> {
+            Value { child: Child<'a>, depth: usize },
+            Text(String),
+        }
+
+        let unit = indent.unit();
+        let mut json = String::new();
+        let mut stack = vec![Piece::Value {
+            child: Child::Built(key),
+            depth: 0,
+        }];
+
+        while let Some(piece) = stack.pop() {
+            let (child, depth) = match piece {
+                Piece::Text(text) => {
+                    json.push_str(&text);
+                    continue;
+                }
+                Piece::Value { child, depth } => (child, depth),
+            };
+
+            let (array, entries) = match self.shape(child) {
+                Shape::Terminal(value, written) => {
+                    json.push_str(&terminal_json(value, written));
+                    continue;
+                }
+                Shape::Container { array, entries } => (array, entries.list(false)),
+            };
+            let (open, close) = brackets(array);
+            json.push_str(open);
+            if entries.is_empty() {
+                json.push_str(close);
+                continue;
+            }
+
+            // pushed in reverse, to be written in order
+            stack.push(Piece::Text(format!("\n{}{close}", unit.repeat(depth))));
+            for (i, (name, child)) in entries.into_iter().enumerate().rev() {
+                stack.push(Piece::Value {
+                    child,
+                    depth: depth + 1,
+                });
+                let mut before = if i > 0 { ",\n" } else { "\n" }.to_string();
+                before.push_str(&unit.repeat(depth + 1));
+                if let Some(name) = name {
+                    // serializing a str cannot fail
+                    before.push_str(&serde_json::to_string(name).unwrap());
+                    before.push_str(": ");
+                }
+                stack.push(Piece::Text(before));
             }
         }
-    }
 
-    pub fn highlight_current_node(&mut self) {
-        let node = self.key_to_node_mut(self.current_node);
-        node.highlighted = true;
+        json
     }
 
-    pub fn toggle_current_node_highlight(&mut self) {
-        let node = self.key_to_node_mut(self.current_node);
-        node.highlighted = !node.highlighted;
-    }
+    /// Writes the value of `key` as [gron](https://github.com/tomnomnom/gron) assignments, one
+    /// line per node from the document's root, in document order. Containers are assigned empty
+    /// first, as gron does, so that the output can be turned back into JSON:
+    ///
+    /// ```
+    /// use json_tui::Tree;
+    ///
+    /// let tree = Tree::try_from_str(r#"{"users": [{"name": "Alice", "e-mail": null}]}"#).unwrap();
+    /// let users = tree.node_at_line(1).unwrap();
+    /// assert_eq!(
+    ///     tree.to_gron(users),
+    ///     "json.users = [];\n\
+    ///      json.users[0] = {};\n\
+    ///      json.users[0].name = \"Alice\";\n\
+    ///      json.users[0][\"e-mail\"] = null;\n"
+    /// );
+    /// ```
+    pub fn to_gron(&self, key: DefaultKey) -> String {
+        let mut gron = String::new();
+        let mut stack = vec![(Child::Built(key), self.path_of(key))];
+
+        while let Some((child, path)) = stack.pop() {
+            let value = match self.shape(child) {
+                Shape::Terminal(value, written) => terminal_json(value, written),
+                Shape::Container { array, entries } => {
+                    for (i, (name, child)) in entries.list(false).into_iter().enumerate().rev() {
+                        let mut path = path.clone();
+                        path.push(match name {
+                            Some(name) => PathSegment::Key(name.to_string()),
+                            None => PathSegment::Index(i),
+                        });
+                        stack.push((child, path));
+                    }
+                    if array { "[]" } else { "{}" }.to_string()
+                }
+            };
+            gron.push_str(&format!("{} = {value};\n", format_gron_path(&path)));
+        }
 
-    pub fn to_text(&self) -> Text<'_> {
-        self.to_text_inner(0, self.root)
+        gron
     }
 
-    pub fn find_current_line(&self) -> usize {
-        let mut line_counter = 0;
-
-        match self.find_line_recursive(&mut line_counter, self.root) {
-            None => line_counter,
-            Some(n) => n,
+    /// Returns the selected value when it is a terminal.
+    pub fn current_terminal(&self) -> Option<&Value> {
+        match &self.key_to_node(self.current_node).node {
+            NodeType::Terminal(v) => Some(v),
+            NodeType::NonTerminal(_) => None,
         }
     }
 
-    fn find_line_recursive(
-        &self,
-        line_counter: &mut usize,
-        current_node: DefaultKey,
-    ) -> Option<usize> {
-        if current_node == self.current_node {
-            return Some(*line_counter);
+    /// Replaces the selected terminal with `v`, marking the tree dirty. Does nothing when the
+    /// selection is a container or `v` is not a terminal value, returning `false`.
+    pub fn set_current_terminal(&mut self, v: Value) -> bool {
+        if matches!(v, Value::Array(_) | Value::Object(_)) {
+            return false;
         }
 
-        let node = self.key_to_node(current_node);
+        if self.current_terminal().is_none() {
+            return false;
+        }
 
-        match &node.node {
-            NodeType::Terminal(_) => (),
-            NodeType::NonTerminal(v) => match &v.node {
-                NonTerminalNode::Array(array) => {
-                    *line_counter += 1;
+        self.replace_current(v);
+        true
+    }
 
-                    for (i, key) in array.iter().enumerate() {
-                        if let Some(n) = self.find_line_recursive(line_counter, *key) {
-                            return Some(n);
-                        }
+    /// Replaces the selected node and its descendants with `value`, marking the tree dirty. The
+    /// node keeps its key, so it stays selected.
+    ///
+    /// ```
+    /// use json_tui::Tree;
+    /// use serde_json::json;
+    ///
+    /// let mut tree = Tree::try_from_str(r#"{"a": [1, 2], "b": true}"#).unwrap();
+    /// let a = tree.node_at_line(1).unwrap();
+    /// tree.select(a);
+    /// tree.replace_current(json!({"c": [null]}));
+    /// assert_eq!(tree.to_value(tree.root()), json!({"a": {"c": [null]}, "b": true}));
+    /// assert_eq!(tree.current_key(), a);
+    /// tree.undo();
+    /// assert_eq!(tree.to_value(tree.root()), json!({"a": [1, 2], "b": true}));
+    /// ```
+    pub fn replace_current(&mut self, value: Value) {
+        let key = self.current_node;
+        let old = self.replace(key, value);
+
+        self.history.record(Change::Replace {
+            path: self.path_of(key),
+            value: old,
+        });
+    }
 
-                        if i < array.len() - 1 {
-                            *line_counter += 1;
-                        }
-                    }
+    /// Puts `value` in place of the node `key` and its descendants, and returns the value it
+    /// held.
+    fn replace(&mut self, key: DefaultKey, value: Value) -> Value {
+        let old = self.to_value(key);
+        let parent = self.key_to_node(key).parent;
+
+        // built apart, then moved under the key of the node it replaces
+        let built = value_to_key(value, &mut self.slot_map, parent);
+        let built = self.slot_map.remove(built).unwrap();
+        if let NodeType::NonTerminal(v) = &built.node {
+            for child in v.node.children(false) {
+                self.key_to_node_mut(child).parent = Some(key);
+            }
+        }
 
-                    *line_counter += 1;
-                }
-                NonTerminalNode::Object(obj) => {
-                    *line_counter += 1;
+        let node = self.key_to_node_mut(key);
+        node.expanded = false;
+        node.written = None;
+        if let NodeType::NonTerminal(v) = std::mem::replace(&mut node.node, built.node) {
+            self.forget(v.node.children(false));
+        }
+        self.mark_modified(key);
 
-                    for (i, (_, key)) in obj.iter().enumerate() {
-                        if let Some(n) = self.find_line_recursive(line_counter, *key) {
-                            return Some(n);
-                        }
+        old
+    }
 
-                        if i < obj.len() - 1 {
-                            *line_counter += 1;
-                        }
-                    }
+    /// The container a node added by [`Tree::insert`] goes into, and its position there.
+    fn insertion_point(&self) -> Option<(DefaultKey, usize)> {
+        let key = self.current_node;
+        let node = self.key_to_node(key);
 
-                    *line_counter += 1;
-                }
-            },
+        if let NodeType::NonTerminal(v) = &node.node
+            && (node.parent.is_none() || v.is_visible() || v.node.is_empty())
+        {
+            return Some((key, v.node.len()));
         }
 
-        None
+        // a lone scalar has nowhere to add to
+        let parent = node.parent?;
+        let NodeType::NonTerminal(v) = &self.key_to_node(parent).node else {
+            unreachable!("a parent is always a container");
+        };
+        let index = v.node.children(false).iter().position(|k| *k == key)?;
+        Some((parent, index + 1))
     }
 
-    fn to_text_inner(&self, indent_level: usize, current_node: DefaultKey) -> Text<'_> {
-        let node = self.key_to_node(current_node);
-
-        let ret = match &node.node {
-            NodeType::Terminal(v) => match v {
-                Value::Number(n) => Text::raw(format!("{n}")),
-                Value::Bool(b) => Text::raw(format!("{b}")),
-                Value::String(s) => Text::raw(format!("\"{s}\"")),
-                Value::Null => Text::raw("{{}}"),
-                _ => unreachable!(),
-            },
-            NodeType::NonTerminal(v) => {
-                if v.is_visible() {
-                    match &v.node {
-                        NonTerminalNode::Array(array) => {
-                            let mut ret = Text::raw("[\n");
-
-                            let indent_level = indent_level + 1;
-                            let indent = Text::raw(Self::INDENT.repeat(indent_level));
-
-                            for (i, v) in array.iter().enumerate() {
-                                let tmp =
-                                    join_text(indent.clone(), self.to_text_inner(indent_level, *v));
-                                ret.extend(tmp);
-
-                                let tmp = if i == (array.len() - 1) {
-                                    let indent = Self::INDENT.repeat(indent_level - 1);
-                                    Text::raw(format!("\n{indent}]"))
-                                } else {
-                                    Text::raw(",\n")
-                                };
-                                ret = join_text(ret, tmp)
-                            }
-                            ret
-                        }
-                        NonTerminalNode::Object(map) => {
-                            let mut ret = Text::raw("{\n");
-
-                            let indent_level = indent_level + 1;
-                            let indent = Text::raw(Self::INDENT.repeat(indent_level));
-
-                            for (i, (key, v)) in map.iter().enumerate() {
-                                ret.extend(Text::raw(format!("{indent}\"{key}\": ")));
-                                ret = join_text(ret, self.to_text_inner(indent_level, *v));
-
-                                let tmp = if i == (map.len() - 1) {
-                                    let indent = Self::INDENT.repeat(indent_level - 1);
-                                    Text::raw(format!("\n{indent}}}"))
-                                } else {
-                                    Text::raw(",\n")
-                                };
-                                ret = join_text(ret, tmp);
-                            }
-                            ret
-                        }
-                    }
-                } else if v.is_array() {
-                    Text::raw("[...]")
-                } else {
-                    Text::raw("{...}")
-                }
-            }
+    /// Whether [`Tree::insert`] adds to an object, taking a key, or to an array. `None` when the
+    /// document is a lone scalar.
+    pub fn inserts_into_object(&self) -> Option<bool> {
+        let (parent, _) = self.insertion_point()?;
+        let NodeType::NonTerminal(v) = &self.key_to_node(parent).node else {
+            unreachable!("only containers are inserted into");
         };
+        Some(!v.is_array())
+    }
 
-        if node.highlighted {
-            ret.lines
-                .into_iter()
-                .map(|l| {
-                    l.spans
-                        .into_iter()
-                        .map(|s| s.white().on_dark_gray())
-                        .collect::<Vec<Span>>()
-                        .into()
-                })
-                .collect::<Vec<Line>>()
-                .into()
-        } else {
-            ret
-        }
+    /// Adds `value`, under `name` when adding to an object, and selects it. It goes last in the
+    /// selection when that is the root or an expanded or empty container, and after the selection
+    /// in its parent otherwise.
+    ///
+    /// ```
+    /// use json_tui::{Tree, path::parse_path};
+    /// use serde_json::json;
+    ///
+    /// let mut tree = Tree::try_from_str(r#"{"a": [1, 3]}"#).unwrap();
+    /// let root = tree.root();
+    /// tree.insert(Some("b".to_string()), json!({"c": null})).unwrap();
+    /// let one = tree.find_path(&parse_path("a[0]").unwrap()).unwrap();
+    /// tree.select(one);
+    /// tree.insert(None, json!(2)).unwrap();
+    /// assert_eq!(tree.to_value(root), json!({"a": [1, 2, 3], "b": {"c": null}}));
+    /// assert!(tree.insert(None, json!(4)).is_ok());
+    /// tree.select(root);
+    /// assert!(tree.insert(Some("a".to_string()), json!(0)).is_err());
+    /// ```
+    pub fn insert(&mut self, name: Option<String>, value: Value) -> Result<DefaultKey, EditError> {
+        let (parent, index) = self.insertion_point().ok_or(EditError::NoContainer)?;
+        self.insert_recorded(parent, index, name, value)
     }
-}
 
-fn join_text<'a>(mut a: Text<'a>, b: Text<'a>) -> Text<'a> {
-    let (b_first, b_rest) = b.lines.split_at(1);
-    for span in b_first[0].spans.iter() {
-        a.push_span(span.clone());
+    /// Adds `value` last in the selected container, under `key_name` when that is an object, and
+    /// selects it. Unlike [`Tree::insert`], a collapsed container is added to rather than next to.
+    ///
+    /// ```
+    /// use json_tui::{Tree, path::parse_path};
+    /// use serde_json::json;
+    ///
+    /// let mut tree = Tree::try_from_str(r#"{"a": [1], "b": 2}"#).unwrap();
+    /// let root = tree.root();
+    /// let a = tree.find_path(&parse_path("a").unwrap()).unwrap();
+    /// tree.set_collapsed(a, true);
+    /// tree.select(a);
+    /// tree.insert_child(None, json!({"c": [3]})).unwrap();
+    /// assert_eq!(tree.to_value(root), json!({"a": [1, {"c": [3]}], "b": 2}));
+    /// let b = tree.find_path(&parse_path("b").unwrap()).unwrap();
+    /// tree.select(b);
+    /// assert!(tree.insert_child(None, json!(4)).is_err());
+    /// ```
+    pub fn insert_child(
+        &mut self,
+        key_name: Option<String>,
+        value: Value,
+    ) -> Result<DefaultKey, EditError> {
+        let parent = self.current_node;
+        let NodeType::NonTerminal(v) = &self.key_to_node(parent).node else {
+            return Err(EditError::NotAContainer);
+        };
+        let index = v.node.len();
+        self.insert_recorded(parent, index, key_name, value)
     }
-    a.extend(Text::from(b_rest.to_vec()));
-    a
-}
 
-pub fn value_to_key(
-    value: Value,
-    slot_map: &mut SlotMap<DefaultKey, Node>,
-    parent: Option<DefaultKey>,
-) -> DefaultKey {
-    match value {
-        v @ (Value::Null | Value::String(_) | Value::Number(_) | Value::Bool(_)) => {
-            let node = NodeType::Terminal(v);
-            let node = Node {
+    /// [`Tree::insert_at`], recorded so that it can be undone.
+    fn insert_recorded(
+        &mut self,
+        parent: DefaultKey,
+        index: usize,
+        name: Option<String>,
+        value: Value,
+    ) -> Result<DefaultKey, EditError> {
+        let key = self.insert_at(parent, index, name, value)?;
+
+        self.history.record(Change::Remove {
+            parent: self.path_of(parent),
+            index,
+        });
+        Ok(key)
+    }
+
+    /// Adds `value` at the end of the root array without it counting as an edit, as for input
+    /// still being read. The selection moves to it when on the last item, or on the root while it
+    /// is empty, to keep up with the input; returns whether it did.
+    ///
+    /// ```
+    /// use json_tui::Tree;
+    /// use serde_json::json;
+    ///
+    /// let mut tree = Tree::try_from_str("[1]").unwrap();
+    /// tree.select(tree.node_at_line(1).unwrap());
+    /// assert_eq!(tree.append_to_root(json!(2)), Ok(true));
+    /// tree.select(tree.node_at_line(1).unwrap());
+    /// assert_eq!(tree.append_to_root(json!({"a": 3})), Ok(false));
+    /// assert_eq!(tree.to_value(tree.root()), json!([1, 2, {"a": 3}]));
+    /// assert!(!tree.is_dirty());
+    /// ```
+    pub fn append_to_root(&mut self, value: Value) -> Result<bool, EditError> {
+        let root = self.root;
+        self.build(root);
+        let following = match self.view(root) {
+            NodeView::Array(items) => match items.last() {
+                Some(&last) => self.current_node == last,
+                None => self.current_node == root,
+            },
+            NodeView::Object(_) => return Err(EditError::KeyMismatch),
+            NodeView::Terminal(_) => return Err(EditError::NoContainer),
+        };
+
+        let key = value_to_key(value, &mut self.slot_map, Some(root));
+        if let NodeType::NonTerminal(v) = &mut self.key_to_node_mut(root).node
+            && let NonTerminalNode::Array(items) = &mut v.node
+        {
+            items.push(key);
+        }
+
+        if following {
+            self.select(key);
+        }
+        Ok(following)
+    }
+
+    /// Adds `value` as the child at `index` of the container `parent`, and selects it.
+    fn insert_at(
+        &mut self,
+        parent: DefaultKey,
+        index: usize,
+        name: Option<String>,
+        value: Value,
+    ) -> Result<DefaultKey, EditError> {
+        self.build(parent);
+        if let (NodeType::NonTerminal(v), Some(name)) = (&self.key_to_node(parent).node, &name)
+            && v.node.entries(false).iter().any(|(n, _)| *n == Some(name))
+        {
+            return Err(EditError::DuplicateKey(name.clone()));
+        }
+
+        let key = value_to_key(value, &mut self.slot_map, Some(parent));
+        let NodeType::NonTerminal(v) = &mut self.key_to_node_mut(parent).node else {
+            unreachable!("only containers are inserted into");
+        };
+        match (&mut v.node, name) {
+            (NonTerminalNode::Array(arr), None) => arr.insert(index, key),
+            (NonTerminalNode::Object(obj), Some(name)) => obj.insert(index, (name, key)),
+            _ => {
+                self.slot_map.remove(key);
+                return Err(EditError::KeyMismatch);
+            }
+        }
+
+        self.mark_modified(key);
+        self.select(key);
+        Ok(key)
+    }
+
+    /// Renames the object entry holding the selected node.
+    pub fn rename_current(&mut self, name: String) -> Result<(), EditError> {
+        let key = self.current_node;
+        let parent = self.key_to_node(key).parent.ok_or(EditError::NotAnEntry)?;
+
+        let NodeType::NonTerminal(v) = &mut self.key_to_node_mut(parent).node else {
+            unreachable!("a parent is always a container");
+        };
+        let NonTerminalNode::Object(entries) = &mut v.node else {
+            return Err(EditError::NotAnEntry);
+        };
+        if entries.iter().any(|(n, k)| *n == name && *k != key) {
+            return Err(EditError::DuplicateKey(name));
+        }
+        let index = entries.iter().position(|(_, k)| *k == key).unwrap();
+        let old = std::mem::replace(&mut entries[index].0, name);
+        self.key_to_node_mut(key).modified = true;
+
+        self.history.record(Change::Rename {
+            parent: self.path_of(parent),
+            index,
+            name: old,
+        });
+        Ok(())
+    }
+
+    /// Removes the selected node and its descendants, selecting its next sibling, else its
+    /// previous one, else its parent. The root cannot be deleted, in which case `false` is
+    /// returned.
+    ///
+    /// ```
+    /// use json_tui::Tree;
+    ///
+    /// let mut tree = Tree::try_from_str("[1, [2], 3]").unwrap();
+    /// let inner = tree.node_at_line(2).unwrap();
+    /// tree.select(tree.node_at_line(5).unwrap());
+    /// assert!(tree.delete_current());
+    /// assert_eq!(tree.current_key(), inner);
+    /// assert!(tree.delete_current());
+    /// assert!(tree.delete_current());
+    /// assert_eq!(tree.current_key(), tree.root());
+    /// assert!(!tree.delete_current());
+    /// ```
+    pub fn delete_current(&mut self) -> bool {
+        let key = self.current_node;
+        if key == self.root {
+            return false;
+        }
+
+        let inverse = self.remove(key);
+        self.history.record(inverse);
+        true
+    }
+
+    /// Removes the child `key` and its descendants, selecting its neighbour as
+    /// [`Tree::delete_current`] does. Returns the change adding it back.
+    fn remove(&mut self, key: DefaultKey) -> Change {
+        let parent = self
+            .key_to_node(key)
+            .parent
+            .expect("the root is not removed");
+        let (name, value) = (self.key_name(key).map(str::to_string), self.to_value(key));
+
+        let NodeType::NonTerminal(v) = &self.key_to_node(parent).node else {
+            unreachable!("a parent is always a container");
+        };
+        let next = v
+            .node
+            .find_next_key(key, self.sort_keys)
+            .or_else(|| v.node.find_previous_key(key, self.sort_keys))
+            .unwrap_or(parent);
+        let index = v
+            .node
+            .children(false)
+            .iter()
+            .position(|k| *k == key)
+            .unwrap();
+        self.select(next);
+
+        let parent_node = self.key_to_node_mut(parent);
+        parent_node.modified = true;
+        if let NodeType::NonTerminal(v) = &mut parent_node.node {
+            match &mut v.node {
+                NonTerminalNode::Array(arr) => arr.retain(|k| *k != key),
+                NonTerminalNode::Object(obj) => obj.retain(|(_, k)| *k != key),
+                NonTerminalNode::Unbuilt(_) => unreachable!("a parent is built"),
+            }
+        }
+
+        self.forget(vec![key]);
+
+        Change::Insert {
+            parent: self.path_of(parent),
+            index,
+            name,
+            value,
+        }
+    }
+
+    /// Drops the nodes `keys`, taken out of their parents, and their descendants.
+    fn forget(&mut self, mut keys: Vec<DefaultKey>) {
+        while let Some(k) = keys.pop() {
+            if let Some(Node {
+                node: NodeType::NonTerminal(v),
+                ..
+            }) = self.slot_map.remove(k)
+            {
+                keys.extend(v.node.children(false));
+            }
+        }
+        self.bookmarks.retain(|k| self.slot_map.contains_key(*k));
+    }
+
+    /// Reverts the last edit, selecting the node it affected. Nodes deleted by the edit come back
+    /// under new keys, so keys held to them stay stale, see [`Tree::contains`]. Returns `false`
+    /// when there is nothing to undo.
+    ///
+    /// ```
+    /// use json_tui::Tree;
+    /// use serde_json::json;
+    ///
+    /// let mut tree = Tree::try_from_str(r#"{"a": [1, 2], "b": true}"#).unwrap();
+    /// let root = tree.root();
+    /// tree.select(tree.node_at_line(1).unwrap());
+    /// tree.delete_current();
+    /// tree.rename_current("c".to_string()).unwrap();
+    /// assert_eq!(tree.to_value(root), json!({"c": true}));
+    ///
+    /// assert!(tree.undo());
+    /// assert!(tree.undo());
+    /// assert_eq!(tree.to_value(root), json!({"a": [1, 2], "b": true}));
+    /// assert!(!tree.undo());
+    /// assert!(tree.redo());
+    /// assert_eq!(tree.to_value(root), json!({"b": true}));
+    /// ```
+    pub fn undo(&mut self) -> bool {
+        let Some(change) = self.history.pop_undo() else {
+            return false;
+        };
+
+        let inverse = self.apply(change);
+        self.history.push_redo(inverse);
+        true
+    }
+
+    /// Makes again the last edit reverted by [`Tree::undo`], unless another edit was made since.
+    pub fn redo(&mut self) -> bool {
+        let Some(change) = self.history.pop_redo() else {
+            return false;
+        };
+
+        let inverse = self.apply(change);
+        self.history.push_undo(inverse);
+        true
+    }
+
+    /// Keeps the last `depth` edits to be undone, 100 by default.
+    pub fn set_undo_depth(&mut self, depth: usize) {
+        self.history.set_depth(depth);
+    }
+
+    /// Makes `change` and returns the change reverting it. Changes come off the history in the
+    /// reverse order they were made in, so the nodes they refer to exist.
+    fn apply(&mut self, change: Change) -> Change {
+        let find = |tree: &mut Self, path: &[PathSegment]| {
+            tree.find_path(path)
+                .expect("a change refers to a node of the tree at that point")
+        };
+
+        match change {
+            Change::Replace { path, value } => {
+                let key = find(self, &path);
+                let old = self.replace(key, value);
+                self.select(key);
+                Change::Replace { path, value: old }
+            }
+            Change::Insert {
+                parent,
+                index,
+                name,
+                value,
+            } => {
+                let key = find(self, &parent);
+                self.insert_at(key, index, name, value)
+                    .expect("a removed child fits back where it was");
+                Change::Remove { parent, index }
+            }
+            Change::Remove { parent, index } => {
+                let key = find(self, &parent);
+                let NodeType::NonTerminal(v) = &self.key_to_node(key).node else {
+                    unreachable!("only containers have children");
+                };
+                let child = v.node.children(false)[index];
+                self.remove(child)
+            }
+            Change::Rename {
                 parent,
-                node,
-                highlighted: false,
+                index,
+                name,
+            } => {
+                let key = find(self, &parent);
+                let NodeType::NonTerminal(HidableValue {
+                    node: NonTerminalNode::Object(entries),
+                    ..
+                }) = &mut self.key_to_node_mut(key).node
+                else {
+                    unreachable!("only object entries are renamed");
+                };
+                let (old, child) = &mut entries[index];
+                let (old, child) = (std::mem::replace(old, name), *child);
+                self.key_to_node_mut(child).modified = true;
+                self.select(child);
+                Change::Rename {
+                    parent,
+                    index,
+                    name: old,
+                }
+            }
+        }
+    }
+
+    /// Number of entries of the container `key`, 0 for a scalar.
+    pub fn child_count(&self, key: DefaultKey) -> usize {
+        match &self.key_to_node(key).node {
+            NodeType::Terminal(_) => 0,
+            NodeType::NonTerminal(v) => v.node.len(),
+        }
+    }
+
+    /// Whether `key` still refers to a node of the tree, which it stops doing once deleted.
+    pub fn contains(&self, key: DefaultKey) -> bool {
+        self.slot_map.contains_key(key)
+    }
+
+    /// Parses `s` as JSON and builds its tree. Object keys keep the order of the document:
+    ///
+    /// ```
+    /// use json_tui::Tree;
+    ///
+    /// let mut tree = Tree::try_from_str(r#"{"z":1,"a":2,"m":3}"#).unwrap();
+    /// let lines = |tree: &Tree| -> Vec<String> {
+    ///     tree.to_text().lines.iter().map(|l| l.to_string()).collect()
+    /// };
+    /// assert_eq!(lines(&tree)[1..4], ["│ \"z\": 1,", "│ \"a\": 2,", "│ \"m\": 3"]);
+    ///
+    /// tree.toggle_sort_keys();
+    /// assert_eq!(lines(&tree)[1..4], ["│ \"a\": 2,", "│ \"m\": 3,", "│ \"z\": 1"]);
+    /// ```
+    ///
+    /// Numbers are rendered as written, without going through `f64`, and the spans of values
+    /// are recorded in `s` as by [`source::record_spans`]:
+    ///
+    /// ```
+    /// use json_tui::Tree;
+    ///
+    /// let tree = Tree::try_from_str("[9007199254740993, -0, -0.0, 0.1000, 1e3, 2.5E-7]").unwrap();
+    /// let lines: Vec<String> = tree.to_text().lines.iter().map(|l| l.to_string()).collect();
+    /// assert_eq!(
+    ///     lines[1..7],
+    ///     ["│ 9007199254740993,", "│ -0,", "│ -0.0,", "│ 0.1000,", "│ 1e3,", "│ 2.5E-7"]
+    /// );
+    /// ```
+    ///
+    /// A byte order mark before the document, as written by some Windows tools, is skipped:
+    ///
+    /// ```
+    /// use json_tui::Tree;
+    /// use serde_json::json;
+    ///
+    /// let tree = Tree::try_from_str("\u{FEFF}{\"a\": 1}\r\n").unwrap();
+    /// assert_eq!(tree.to_value(tree.root()), json!({"a": 1}));
+    /// ```
+    pub fn try_from_str(s: &str) -> Result<Self, TreeError> {
+        let s = s.strip_prefix('\u{FEFF}').unwrap_or(s);
+        if s.trim().is_empty() {
+            return Err(TreeError::Empty);
+        }
+
+        let value = source::read_value(s)?;
+        let mut tree = Self::from_value(value);
+        source::record_spans(&mut tree, s, false);
+        Ok(tree)
+    }
+
+    /// Collapses or expands the selected container, or shows a truncated string in full. An
+    /// expanded container gives the selection to its first child with
+    /// [`Tree::set_select_expanded`]:
+    ///
+    /// ```
+    /// use json_tui::Tree;
+    ///
+    /// let mut tree = Tree::try_from_str(r#"{"a": {"b": 1}}"#).unwrap();
+    /// tree.set_select_expanded(true);
+    /// let a = tree.node_at_line(1).unwrap();
+    /// tree.select(a);
+    /// tree.toggle_current_node_visibility();
+    /// assert_eq!(tree.current_key(), a);
+    /// tree.toggle_current_node_visibility();
+    /// assert_eq!(tree.current_key(), tree.node_at_line(2).unwrap());
+    /// ```
+    pub fn toggle_current_node_visibility(&mut self) {
+        // only collapsed containers are left unbuilt, so this one is being expanded
+        self.build(self.current_node);
+        let sort_keys = self.sort_keys;
+        let node = self.key_to_node_mut(self.current_node);
+        let first_shown = match &mut node.node {
+            NodeType::Terminal(_) => {
+                node.expanded = !node.expanded;
+                None
+            }
+            NodeType::NonTerminal(v) => {
+                v.toggle_visibility();
+                v.is_visible()
+                    .then(|| v.node.children(sort_keys).first().copied())
+                    .flatten()
+            }
+        };
+
+        if let Some(first) = first_shown.filter(|_| self.select_expanded) {
+            self.current_node = first;
+        }
+    }
+
+    pub fn set_select_expanded(&mut self, select_expanded: bool) {
+        self.select_expanded = select_expanded;
+    }
+
+    /// Collapses each container once [`Tree::next_node_down`] or [`Tree::next_node_up`] leave
+    /// it, so that only the containers around the selection stay expanded:
+    ///
+    /// ```
+    /// use json_tui::Tree;
+    ///
+    /// let mut tree = Tree::try_from_str(r#"{"a": {"b": [1]}, "c": 2}"#).unwrap();
+    /// tree.set_focus_mode(true);
+    /// for _ in 0..4 {
+    ///     tree.next_node_down();
+    /// }
+    /// let lines: Vec<String> = tree.to_text().lines.iter().map(|l| l.to_string()).collect();
+    /// assert_eq!(lines, ["{", "│ \"a\": {… 1 key},", "│ \"c\": 2", "}"]);
+    /// ```
+    pub fn set_focus_mode(&mut self, focus_mode: bool) {
+        self.focus_mode = focus_mode;
+    }
+
+    pub fn is_focus_mode(&self) -> bool {
+        self.focus_mode
+    }
+
+    /// Collapses `left` and the containers holding it, up to the first one holding the
+    /// selection as well.
+    fn collapse_left(&mut self, left: DefaultKey) {
+        let mut kept = HashSet::new();
+        let mut key = Some(self.current_node);
+        while let Some(k) = key {
+            kept.insert(k);
+            key = self.key_to_node(k).parent;
+        }
+
+        let mut key = Some(left);
+        while let Some(k) = key.filter(|k| !kept.contains(k)) {
+            if let NodeType::NonTerminal(v) = &self.key_to_node(k).node
+                && v.is_visible()
+                && !v.node.is_empty()
+            {
+                self.set_collapsed(k, true);
+            }
+            key = self.key_to_node(k).parent;
+        }
+    }
+
+    /// Whether the document was edited since it was loaded or last saved, undoing back to
+    /// either leaving it clean.
+    ///
+    /// ```
+    /// use json_tui::Tree;
+    ///
+    /// let mut tree = Tree::try_from_str("[1, 2]").unwrap();
+    /// tree.select(tree.node_at_line(1).unwrap());
+    /// tree.delete_current();
+    /// assert!(tree.is_dirty());
+    /// tree.undo();
+    /// assert!(!tree.is_dirty());
+    /// tree.redo();
+    /// tree.mark_clean();
+    /// tree.undo();
+    /// assert!(tree.is_dirty());
+    /// tree.redo();
+    /// assert!(!tree.is_dirty());
+    /// ```
+    pub fn is_dirty(&self) -> bool {
+        !self.history.is_saved()
+    }
+
+    /// Notes that the document was saved, which clears [`Tree::is_modified`].
+    pub fn mark_clean(&mut self) {
+        self.history.mark_saved();
+        for node in self.slot_map.values_mut() {
+            node.modified = false;
+        }
+        self.invalidate();
+    }
+
+    pub fn theme(&self) -> &Theme {
+        &self.theme
+    }
+
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+        self.invalidate();
+    }
+
+    /// Flags `keys` as search matches, clearing any previous ones.
+    pub fn mark_matches(&mut self, keys: &[DefaultKey]) {
+        self.invalidate();
+        for (_, node) in self.slot_map.iter_mut() {
+            node.matched = false;
+        }
+
+        for key in keys {
+            self.key_to_node_mut(*key).matched = true;
+        }
+    }
+
+    /// Object entries keep their original order and are only sorted when displayed.
+    pub fn toggle_sort_keys(&mut self) {
+        self.sort_keys = !self.sort_keys;
+        self.invalidate();
+    }
+
+    pub fn set_sort_keys(&mut self, sort_keys: bool) {
+        self.sort_keys = sort_keys;
+        self.invalidate();
+    }
+
+    /// Draws containers of a few terminals on one line, when it fits in 60 columns, wide
+    /// characters such as CJK taking two:
+    ///
+    /// ```
+    /// use json_tui::Tree;
+    ///
+    /// let wide = "漢".repeat(18);
+    /// let mut tree = Tree::try_from_str(&format!(r#"{{"a": {{"b": "{wide}"}}}}"#)).unwrap();
+    /// tree.set_compact(true);
+    /// assert_eq!(tree.to_text().lines[1].to_string(), format!("│ \"a\": {{\"b\": \"{wide}\"}}"));
+    ///
+    /// let wider = "漢".repeat(28);
+    /// let mut tree = Tree::try_from_str(&format!(r#"{{"a": {{"b": "{wider}"}}}}"#)).unwrap();
+    /// tree.set_compact(true);
+    /// assert_eq!(tree.to_text().lines[1].to_string(), "│ \"a\": {");
+    /// ```
+    pub fn set_compact(&mut self, compact: bool) {
+        self.compact = compact;
+        self.invalidate();
+    }
+
+    pub fn toggle_compact(&mut self) {
+        self.compact = !self.compact;
+        self.invalidate();
+    }
+
+    /// Shows the newlines of strings as line breaks, with the following lines aligned under the
+    /// first one, instead of as `\n` escapes.
+    pub fn toggle_multiline_strings(&mut self) {
+        self.multiline_strings = !self.multiline_strings;
+        self.invalidate();
+    }
+
+    /// Writes integers with a `,` between groups of three digits, e.g. `1,000,000`. Only the
+    /// display changes, floats and exponents are left as they are.
+    pub fn toggle_group_digits(&mut self) {
+        self.group_digits = !self.group_digits;
+        self.invalidate();
+    }
+
+    /// Follows numbers that look like unix timestamps with their date, as a dimmed comment.
+    pub fn toggle_timestamps(&mut self) {
+        self.timestamps = !self.timestamps;
+        self.invalidate();
+    }
+
+    /// Strings longer than `limit` characters are cut until expanded, 0 meaning no limit.
+    pub fn set_string_limit(&mut self, limit: usize) {
+        self.string_limit = limit;
+        self.invalidate();
+    }
+
+    /// Quotes strings and keys on screen with `quotes`:
+    ///
+    /// ```
+    /// use json_tui::{Tree, node::QuoteStyle};
+    ///
+    /// let mut tree = Tree::try_from_str(r#"{"it's": "a \"b\""}"#).unwrap();
+    /// tree.set_quote_style(QuoteStyle::Single);
+    /// assert_eq!(tree.to_text().lines[1].to_string(), r#"│ 'it\'s': 'a "b"'"#);
+    /// tree.set_quote_style(QuoteStyle::Bare);
+    /// assert_eq!(tree.to_text().lines[1].to_string(), r#"│ it's: a "b""#);
+    /// ```
+    pub fn set_quote_style(&mut self, quotes: QuoteStyle) {
+        self.quotes = quotes;
+        self.invalidate();
+    }
+
+    pub fn set_indent(&mut self, indent: Indent) {
+        self.indent = indent;
+        self.invalidate();
+    }
+
+    /// Draws the indentation as dimmed `│` guides, or as plain spaces for copy-friendly output.
+    pub fn toggle_indent_guides(&mut self) {
+        self.indent_guides = !self.indent_guides;
+        self.invalidate();
+    }
+
+    /// Shows collapsed containers as a preview of their first children instead of their size.
+    pub fn set_collapsed_preview(&mut self, preview: bool) {
+        self.collapsed_preview = preview;
+        self.invalidate();
+    }
+
+    /// Width, in columns, that previews of collapsed containers are cut to.
+    pub fn set_view_width(&mut self, width: usize) {
+        // called on every draw, the text is only outdated when previews are cut differently
+        if width != self.view_width {
+            self.view_width = width;
+            self.invalidate();
+        }
+    }
+
+    /// In compact mode, visible non-empty containers holding only terminals are drawn on a
+    /// single line when that line fits in `COMPACT_WIDTH` columns.
+    fn is_inline(&self, key: DefaultKey) -> bool {
+        if !self.compact {
+            return false;
+        }
+
+        let NodeType::NonTerminal(v) = &self.key_to_node(key).node else {
+            return false;
+        };
+
+        if !v.is_visible() {
+            return false;
+        }
+
+        let children = v.node.children(self.sort_keys);
+        if children.is_empty() {
+            return false;
+        }
+
+        // brackets plus ", " between children
+        let mut width = 2 + (children.len() - 1) * 2;
+
+        for child in children {
+            let node = self.key_to_node(child);
+            match &node.node {
+                NodeType::Terminal(_) if self.terminal_line_count(child) > 1 => return false,
+                NodeType::Terminal(_) => width += node.terminal_text(self.quotes).width(),
+                NodeType::NonTerminal(_) => return false,
+            }
+
+            if let Some(name) = self.key_name(child) {
+                width += self.quotes.quoted(name).width() + 2;
+            }
+
+            if width > Self::COMPACT_WIDTH {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    pub fn highlight_current_node(&mut self) {
+        self.highlight = true;
+    }
+
+    pub fn toggle_current_node_highlight(&mut self) {
+        self.highlight = !self.highlight;
+    }
+
+    /// Returns the key of the selected node.
+    pub fn current_key(&self) -> DefaultKey {
+        self.current_node
+    }
+
+    /// Moves the selection to `key`, expanding every collapsed ancestor so it is rendered.
+    pub fn select(&mut self, key: DefaultKey) {
+        let mut parent = self.key_to_node(key).parent;
+        while let Some(k) = parent {
+            // clicks and jumps within the shown tree keep the rendered text
+            if !self.key_to_node(k).is_visible() {
+                self.set_collapsed(k, false);
+            }
+            parent = self.key_to_node(k).parent;
+        }
+
+        self.current_node = key;
+        self.highlight_current_node();
+    }
+
+    /// Returns the segments leading from the root to `key`.
+    pub fn path_of(&self, key: DefaultKey) -> Vec<PathSegment> {
+        let mut segments = vec![];
+        let mut current = key;
+
+        while let Some(parent) = self.key_to_node(current).parent {
+            match &self.key_to_node(parent).node {
+                NodeType::NonTerminal(v) => match &v.node {
+                    NonTerminalNode::Array(array) => {
+                        let i = array.iter().position(|k| *k == current).unwrap();
+                        segments.push(PathSegment::Index(i));
+                    }
+                    NonTerminalNode::Object(obj) => {
+                        let (name, _) = obj.iter().find(|(_, k)| *k == current).unwrap();
+                        segments.push(PathSegment::Key(name.clone()));
+                    }
+                    NonTerminalNode::Unbuilt(_) => unreachable!("a parent is built"),
+                },
+                NodeType::Terminal(_) => unreachable!(),
+            }
+
+            current = parent;
+        }
+
+        segments.reverse();
+        segments
+    }
+
+    /// Returns the path of the selected node.
+    pub fn current_path(&self) -> Vec<PathSegment> {
+        self.path_of(self.current_node)
+    }
+
+    /// Returns the node reached by following `path` from the root, if every segment exists,
+    /// building the containers it goes through.
+    pub fn find_path(&mut self, path: &[PathSegment]) -> Option<DefaultKey> {
+        let mut current = self.root;
+
+        for segment in path {
+            self.build(current);
+            let NodeType::NonTerminal(v) = &self.key_to_node(current).node else {
+                return None;
+            };
+
+            current = match (&v.node, segment) {
+                (NonTerminalNode::Array(array), PathSegment::Index(i)) => *array.get(*i)?,
+                (NonTerminalNode::Object(obj), PathSegment::Key(name)) => {
+                    obj.iter().find(|(n, _)| n == name).map(|(_, k)| *k)?
+                }
+                _ => return None,
             };
-            slot_map.insert(node)
         }
-        Value::Object(map) => {
-            let node = Node {
-                parent,
-                highlighted: false,
-                node: NodeType::NonTerminal(HidableValue {
-                    visible: true,
-                    node: NonTerminalNode::Object(vec![]),
-                }),
+
+        Some(current)
+    }
+
+    /// Returns the paths of every collapsed container.
+    pub fn collapsed_paths(&self) -> Vec<Vec<PathSegment>> {
+        self.slot_map
+            .iter()
+            .filter(|(_, node)| !node.is_visible())
+            .map(|(key, _)| self.path_of(key))
+            .collect()
+    }
+
+    pub fn set_collapsed(&mut self, key: DefaultKey, collapsed: bool) {
+        if !collapsed {
+            self.build(key);
+        }
+        if let NodeType::NonTerminal(v) = &mut self.key_to_node_mut(key).node {
+            v.visible = !collapsed;
+        }
+    }
+
+    /// Expands the containers less than `depth` levels deep and collapses the others, the root
+    /// being the only one at depth 0, so that `0` shows nothing but the root collapsed. The
+    /// selection moves out of any container it ends up hidden in.
+    ///
+    /// ```
+    /// use json_tui::Tree;
+    ///
+    /// let mut tree = Tree::try_from_str(r#"{"a": {"b": [1]}, "c": 2}"#).unwrap();
+    /// tree.expand_to_depth(1);
+    /// let lines: Vec<String> = tree.to_text().lines.iter().map(|l| l.to_string()).collect();
+    /// assert_eq!(lines, ["{", "│ \"a\": {… 1 key},", "│ \"c\": 2", "}"]);
+    /// tree.expand_to_depth(0);
+    /// assert_eq!(tree.to_text().lines.len(), 1);
+    /// ```
+    pub fn expand_to_depth(&mut self, depth: usize) {
+        let mut stack = vec![(self.root, 0)];
+
+        while let Some((key, level)) = stack.pop() {
+            if let NodeType::NonTerminal(_) = &self.key_to_node(key).node {
+                // built by being expanded, and left unbuilt when collapsed
+                self.set_collapsed(key, level >= depth);
+                let NodeType::NonTerminal(v) = &self.key_to_node(key).node else {
+                    unreachable!("a container stays one once built");
+                };
+                stack.extend(v.node.children(false).into_iter().map(|k| (k, level + 1)));
+            }
+        }
+
+        // the outermost collapsed container around the selection takes it
+        let mut parent = self.key_to_node(self.current_node).parent;
+        while let Some(k) = parent {
+            if !self.key_to_node(k).is_visible() {
+                self.current_node = k;
+            }
+            parent = self.key_to_node(k).parent;
+        }
+        self.highlight_current_node();
+    }
+
+    /// Collapses every array of more than `threshold` values that holds no object or array, as
+    /// long lists of numbers take the whole view. The root is left expanded.
+    ///
+    /// ```
+    /// use json_tui::Tree;
+    ///
+    /// let mut tree = Tree::try_from_str(r#"{"a": [1, 2, 3], "b": [1, 2], "c": [[1], 2, 3]}"#).unwrap();
+    /// tree.auto_collapse_scalar_arrays(2);
+    /// let lines: Vec<String> = tree.to_text().lines.iter().map(|l| l.to_string()).collect();
+    /// assert_eq!(lines[1], "│ \"a\": [… 3 items],");
+    /// assert_eq!(lines[2], "│ \"b\": [");
+    /// ```
+    pub fn auto_collapse_scalar_arrays(&mut self, threshold: usize) {
+        let arrays: Vec<DefaultKey> = self
+            .slot_map
+            .iter()
+            .filter(|(key, node)| {
+                let NodeType::NonTerminal(v) = &node.node else {
+                    return false;
+                };
+                let NonTerminalNode::Array(items) = &v.node else {
+                    return false;
+                };
+                *key != self.root
+                    && items.len() > threshold
+                    && items
+                        .iter()
+                        .all(|k| matches!(self.key_to_node(*k).node, NodeType::Terminal(_)))
+            })
+            .map(|(key, _)| key)
+            .collect();
+
+        for key in arrays {
+            self.set_collapsed(key, true);
+        }
+    }
+
+    /// Describes a node as `(label, value)` pairs: its JSON type and, for terminals, the raw
+    /// value along with its length or numeric sub-type.
+    pub fn details(&self, key: DefaultKey) -> Vec<(&'static str, String)> {
+        let node = self.key_to_node(key);
+        match &node.node {
+            NodeType::Terminal(v) => {
+                let mut details = vec![("type", value_type(v).to_string())];
+
+                match v {
+                    Value::String(s) => {
+                        details.push(("value", s.clone()));
+                        details.push(("length", format!("{} chars", s.chars().count())));
+                    }
+                    Value::Number(n) => {
+                        details.push(("value", node.terminal_json()));
+                        let kind = if is_float(n) { "float" } else { "integer" };
+                        details.push(("number", kind.to_string()));
+                    }
+                    _ => details.push(("value", node.terminal_json())),
+                }
+
+                details
+            }
+            NodeType::NonTerminal(v) => {
+                let kind = if v.is_array() { "array" } else { "object" };
+                vec![
+                    ("type", kind.to_string()),
+                    ("children", v.node.len().to_string()),
+                ]
+            }
+        }
+    }
+
+    /// Counts the values of the document by type, collapsed or not.
+    ///
+    /// ```
+    /// use json_tui::Tree;
+    ///
+    /// let tree = Tree::try_from_str(r#"{"a": [1, "x", null], "b": {"c": true}}"#).unwrap();
+    /// let stats = tree.stats();
+    /// assert_eq!((stats.objects, stats.arrays, stats.strings), (2, 1, 1));
+    /// assert_eq!((stats.numbers, stats.booleans, stats.nulls), (1, 1, 1));
+    /// assert_eq!((stats.max_depth, stats.nodes()), (2, 7));
+    /// ```
+    pub fn stats(&self) -> TreeStats {
+        let mut stats = TreeStats::default();
+        let mut stack = vec![(Child::Built(self.root), 0)];
+
+        while let Some((child, depth)) = stack.pop() {
+            stats.max_depth = stats.max_depth.max(depth);
+
+            match self.shape(child) {
+                Shape::Container { array, entries } => {
+                    if array {
+                        stats.arrays += 1;
+                    } else {
+                        stats.objects += 1;
+                    }
+                    let children = entries.list(false).into_iter();
+                    stack.extend(children.map(|(_, child)| (child, depth + 1)));
+                }
+                Shape::Terminal(Value::String(_), _) => stats.strings += 1,
+                Shape::Terminal(Value::Number(_), _) => stats.numbers += 1,
+                Shape::Terminal(Value::Bool(_), _) => stats.booleans += 1,
+                Shape::Terminal(..) => stats.nulls += 1,
+            }
+        }
+
+        stats
+    }
+
+    /// One-line rendering of a node, with containers shown as their collapsed summary.
+    pub fn summary(&self, key: DefaultKey) -> String {
+        let node = self.key_to_node(key);
+        match &node.node {
+            NodeType::Terminal(_) => node.terminal_text(self.quotes),
+            NodeType::NonTerminal(v) => v.summary(),
+        }
+    }
+
+    /// One-line preview of a container's first children, e.g. `{… "name": "Alice", "age": 31, …}`,
+    /// at most `max_width` columns wide. Nested containers are shown as `{…}` and long scalars
+    /// are cut, so the cost only depends on `max_width`, not on the size of the values.
+    ///
+    /// ```
+    /// use json_tui::Tree;
+    ///
+    /// let tree = Tree::try_from_str(r#"["日本語です", 1]"#).unwrap();
+    /// assert_eq!(tree.preview(tree.root(), 12), "[… \"日…\", …]");
+    /// ```
+    pub fn preview(&self, key: DefaultKey, max_width: usize) -> String {
+        let (array, entries) = match self.shape(Child::Built(key)) {
+            Shape::Terminal(value, written) => {
+                return truncated_text(value, written, max_width, self.quotes);
+            }
+            Shape::Container { array, entries } => (array, entries),
+        };
+
+        let (open, close) = brackets(array);
+        let mut preview = format!("{open}…");
+        // keep room for the closing bracket and a trailing ", …"
+        let mut budget = max_width.saturating_sub(open.len() + close.len() + 4);
+        let mut shown = 0;
+        let entries = entries.list(self.sort_keys);
+
+        for (name, child) in &entries {
+            let separator = if shown == 0 { " " } else { ", " };
+            let mut item = separator.to_string();
+            if let Some(name) = name {
+                item.push_str(&truncated_quoted(name, budget, self.quotes));
+                item.push_str(": ");
+            }
+            let room = budget.saturating_sub(item.width());
+            match self.shape(*child) {
+                Shape::Terminal(value, written) => {
+                    item.push_str(&truncated_text(value, written, room, self.quotes))
+                }
+                Shape::Container { array, .. } => {
+                    let (open, close) = brackets(array);
+                    item.push_str(&format!("{open}…{close}"));
+                }
+            }
+
+            let width = item.width();
+            if width > budget {
+                break;
+            }
+            budget -= width;
+            preview.push_str(&item);
+            shown += 1;
+        }
+
+        if shown > 0 && shown < entries.len() {
+            preview.push_str(", …");
+        }
+        preview.push_str(close);
+        preview
+    }
+
+    /// The value of `key` written on one line as compact JSON, e.g. `{"a": [1, 2], "b": null}`,
+    /// cut with `…` to at most `max_width` columns. Writing stops once past `max_width`, so that
+    /// big containers are previewed as fast as small ones.
+    ///
+    /// ```
+    /// use json_tui::Tree;
+    ///
+    /// let tree = Tree::try_from_str(r#"{"a": [1, 2], "b": {}, "c": "xyz"}"#).unwrap();
+    /// let root = tree.root();
+    /// assert_eq!(tree.compact_preview(root, 40), r#"{"a": [1, 2], "b": {}, "c": "xyz"}"#);
+    /// assert_eq!(tree.compact_preview(root, 12), r#"{"a": [1, 2…"#);
+    /// ```
+    pub fn compact_preview(&self, key: DefaultKey, max_width: usize) -> String {
+        enum Piece<'a> {
+            Node {
+                name: Option<&'a str>,
+                child: Child<'a>,
+                first: bool,
+            },
+            Close(&'static str),
+        }
+
+        let mut preview = String::new();
+        let mut width = 0;
+        let mut stack = vec![Piece::Node {
+            name: None,
+            child: Child::Built(key),
+            first: true,
+        }];
+
+        while width <= max_width
+            && let Some(piece) = stack.pop()
+        {
+            let mut text = String::new();
+            match piece {
+                Piece::Close(close) => text.push_str(close),
+                Piece::Node { name, child, first } => {
+                    if !first {
+                        text.push_str(", ");
+                    }
+                    if let Some(name) = name {
+                        text.push_str(&truncated_quoted(name, max_width + 1, self.quotes));
+                        text.push_str(": ");
+                    }
+                    // cut past the room left, the preview being cut at the end
+                    let room = max_width.saturating_sub(width + text.width()) + 1;
+                    match self.shape(child) {
+                        Shape::Terminal(value, written) => {
+                            text.push_str(&truncated_text(value, written, room, self.quotes))
+                        }
+                        Shape::Container { array, entries } => {
+                            let (open, close) = brackets(array);
+                            text.push_str(open);
+                            stack.push(Piece::Close(close));
+                            let entries = entries.list(self.sort_keys);
+                            stack.extend(entries.into_iter().enumerate().rev().map(
+                                |(i, (name, child))| Piece::Node {
+                                    name,
+                                    child,
+                                    first: i == 0,
+                                },
+                            ));
+                        }
+                    }
+                }
+            }
+            width += text.width();
+            preview.push_str(&text);
+        }
+
+        if width > max_width {
+            let mut cut = start_of_width(&preview, max_width.saturating_sub(1)).to_string();
+            cut.push('…');
+            return cut;
+        }
+        preview
+    }
+
+    /// Whether `key` is a container with children, collapsed.
+    pub fn is_collapsed(&self, key: DefaultKey) -> bool {
+        match &self.key_to_node(key).node {
+            NodeType::NonTerminal(v) => !v.is_visible() && !v.node.is_empty(),
+            NodeType::Terminal(_) => false,
+        }
+    }
+
+    /// Returns the name under which `key` is stored in its parent, if the parent is an object.
+    pub fn key_name(&self, key: DefaultKey) -> Option<&str> {
+        let parent = self.key_to_node(key).parent?;
+
+        match &self.key_to_node(parent).node {
+            NodeType::NonTerminal(HidableValue {
+                node: NonTerminalNode::Object(obj),
+                ..
+            }) => obj
+                .iter()
+                .find(|(_, k)| *k == key)
+                .map(|(name, _)| name.as_str()),
+            _ => None,
+        }
+    }
+
+    /// Returns, in document order, every node whose value or object key contains `query`.
+    /// Children of collapsed containers are only searched when `include_collapsed` is set, which
+    /// builds those left unbuilt.
+    pub fn find_matches(&mut self, query: &str, include_collapsed: bool) -> Vec<DefaultKey> {
+        if query.is_empty() {
+            return vec![];
+        }
+        if include_collapsed {
+            self.build_all();
+        }
+
+        self.find_nodes(include_collapsed, |_, node, name| {
+            let value_matches = match &node.node {
+                NodeType::Terminal(Value::String(s)) => s.contains(query),
+                NodeType::Terminal(_) => node.terminal_json().contains(query),
+                NodeType::NonTerminal(_) => false,
             };
-            let parent_key = slot_map.insert(node);
 
-            let mut vec = vec![];
+            value_matches || name.is_some_and(|n| n.contains(query))
+        })
+    }
+
+    /// Returns, in document order, every object entry whose key is exactly `name`.
+    pub fn find_key_matches(&mut self, name: &str, include_collapsed: bool) -> Vec<DefaultKey> {
+        if include_collapsed {
+            self.build_all();
+        }
+        self.find_nodes(include_collapsed, |_, _, n| n == Some(name))
+    }
+
+    /// Bookmarks the selected node, or removes its bookmark, returning whether it is now set.
+    pub fn toggle_bookmark(&mut self) -> bool {
+        let key = self.current_node;
+        if self.bookmarks.remove(&key) {
+            return false;
+        }
+        self.bookmarks.insert(key)
+    }
+
+    /// Returns the first bookmarked node after the selection in document order, starting over
+    /// from the top past the last one.
+    ///
+    /// ```
+    /// use json_tui::Tree;
+    ///
+    /// let mut tree = Tree::try_from_str(r#"{"a": 1, "b": [2, 3], "c": 4}"#).unwrap();
+    /// assert_eq!(tree.next_bookmark(), None);
+    /// let a = tree.node_at_line(1).unwrap();
+    /// let three = tree.node_at_line(4).unwrap();
+    /// for key in [three, a] {
+    ///     tree.select(key);
+    ///     tree.toggle_bookmark();
+    /// }
+    /// assert_eq!(tree.next_bookmark(), Some(three));
+    /// tree.select(three);
+    /// assert_eq!(tree.next_bookmark(), Some(a));
+    /// ```
+    pub fn next_bookmark(&self) -> Option<DefaultKey> {
+        let keys = self.find_nodes(true, |key, _, _| {
+            key == self.current_node || self.bookmarks.contains(&key)
+        });
+        let current = keys.iter().position(|k| *k == self.current_node)?;
+
+        let after = keys[current + 1..].iter();
+        let before = keys[..=current].iter();
+        after
+            .chain(before)
+            .find(|k| self.bookmarks.contains(k))
+            .copied()
+    }
+
+    /// Lines of [`Tree::to_text`] showing bookmarked nodes, or the collapsed containers hiding
+    /// them.
+    pub fn bookmarked_lines(&self) -> Vec<usize> {
+        self.bookmarks.iter().map(|k| self.line_of(*k)).collect()
+    }
+
+    fn find_nodes(
+        &self,
+        include_collapsed: bool,
+        predicate: impl Fn(DefaultKey, &Node, Option<&str>) -> bool,
+    ) -> Vec<DefaultKey> {
+        let mut matches = vec![];
+        let mut stack = vec![(self.root, None::<&str>)];
+
+        while let Some((key, name)) = stack.pop() {
+            let node = self.key_to_node(key);
 
-            for (k, v) in map {
-                let key = value_to_key(v, slot_map, Some(parent_key));
-                vec.push((k, key));
+            if predicate(key, node, name) {
+                matches.push(key);
             }
 
-            // we know all this is safe by construction
-            let node = slot_map.get_mut(parent_key).unwrap();
+            match &node.node {
+                NodeType::NonTerminal(v) if v.is_visible() || include_collapsed => {
+                    let entries = v.node.entries(self.sort_keys);
+                    stack.extend(entries.into_iter().rev().map(|(n, k)| (k, n)));
+                }
+                _ => (),
+            }
+        }
+
+        matches
+    }
+
+    /// Renders the tree as pretty-printed JSON, collapsed containers standing on one line. The
+    /// text is kept until the tree changes, and shared rather than copied in the meantime.
+    pub fn to_text(&self) -> Arc<Text<'static>> {
+        if let Some(text) = &*self.rendered.borrow() {
+            return Arc::clone(text);
+        }
 
-            match &mut node.node {
-                NodeType::NonTerminal(hv) => match &mut hv.node {
-                    NonTerminalNode::Object(v) => {
-                        let _ = std::mem::replace(v, vec);
+        let mut lines: Vec<Line> = vec![];
+        let mut line: Vec<Span> = vec![];
+        // width of `line` so far
+        let mut column = 0;
+
+        // an explicit stack keeps deeply nested documents from overflowing the call stack
+        let mut stack = vec![RenderStep::Node {
+            key: self.root,
+            indent_level: 0,
+        }];
+
+        while let Some(step) = stack.pop() {
+            match step {
+                RenderStep::Node { key, indent_level } => {
+                    let steps = self.node_render_steps(key, indent_level);
+                    stack.extend(steps.into_iter().rev());
+                }
+                RenderStep::Raw { text, style } if !text.contains('\n') => {
+                    column += text.width();
+                    line.push(Span::styled(text, style));
+                }
+                RenderStep::Raw { text, style } => {
+                    // following lines start under the first character of the text
+                    let start = column;
+                    for (i, part) in text.split('\n').enumerate() {
+                        if i > 0 {
+                            lines.push(std::mem::take(&mut line).into());
+                            line.push(Span::raw(" ".repeat(start)));
+                            column = start;
+                        }
+                        column += part.width();
+                        line.push(Span::styled(part.to_string(), style));
                     }
-                    _ => unreachable!(),
-                },
-                _ => unreachable!(),
+                }
+                RenderStep::NewLine => {
+                    lines.push(std::mem::take(&mut line).into());
+                    column = 0;
+                }
             }
-            parent_key
         }
-        Value::Array(array) => {
-            let node = Node {
-                parent,
-                highlighted: false,
-                node: NodeType::NonTerminal(HidableValue {
-                    visible: true,
-                    node: NonTerminalNode::Array(vec![]),
-                }),
+
+        lines.push(line.into());
+        let text = Arc::new(Text::from(lines));
+        *self.rendered.borrow_mut() = Some(Arc::clone(&text));
+        text
+    }
+
+    /// Returns the index, within [`Tree::to_text`], of the selected node's first line. It is
+    /// counted with [`Tree::line_count`], which takes a collapsed container for the one line it
+    /// is drawn on:
+    ///
+    /// ```
+    /// use json_tui::Tree;
+    ///
+    /// let mut tree = Tree::try_from_str(r#"{"a": {"b": [1, 2]}, "c": "x\ny", "d": 3}"#).unwrap();
+    /// tree.toggle_multiline_strings();
+    /// tree.next_node_down();
+    /// tree.toggle_current_node_visibility();
+    ///
+    /// let text = tree.to_text();
+    /// for (name, line) in [("a", 1), ("c", 2), ("d", 4)] {
+    ///     assert_eq!(tree.find_current_line(), line);
+    ///     assert!(text.lines[line].to_string().contains(&format!("\"{name}\": ")));
+    ///     tree.next_node_down();
+    /// }
+    /// ```
+    pub fn find_current_line(&self) -> usize {
+        self.line_of(self.current_node)
+    }
+
+    /// Index of the first line `key` is drawn on, or of its outermost collapsed ancestor's
+    /// line when it is hidden.
+    pub fn line_of(&self, key: DefaultKey) -> usize {
+        let mut ancestors = vec![key];
+        let mut parent = self.key_to_node(key).parent;
+        while let Some(key) = parent {
+            ancestors.push(key);
+            parent = self.key_to_node(key).parent;
+        }
+
+        let mut line = 0;
+        for pair in ancestors.windows(2).rev() {
+            let (child, parent) = (pair[0], pair[1]);
+            // children of a container drawn on one line share that line
+            if !self.is_expanded(parent) {
+                break;
+            }
+
+            let NodeType::NonTerminal(v) = &self.key_to_node(parent).node else {
+                unreachable!("a parent is always a container");
+            };
+
+            // the opening bracket, then every line of the previous siblings
+            line += 1;
+            for sibling in v.node.children(self.sort_keys) {
+                if sibling == child {
+                    break;
+                }
+                line += self.line_count(sibling);
+            }
+        }
+
+        line
+    }
+
+    /// Lines of [`Tree::to_text`] drawn as selected, as a half-open range that is empty while the
+    /// selection is hidden. An expanded container is selected by its opening line, a node sharing
+    /// its line with others by the whole line.
+    ///
+    /// ```
+    /// use json_tui::Tree;
+    ///
+    /// let mut tree = Tree::try_from_str(r#"{"a": [1, 2], "b": 3}"#).unwrap();
+    /// assert_eq!(tree.selected_line_range(), (0, 1));
+    /// tree.select(tree.node_at_line(5).unwrap());
+    /// assert_eq!(tree.selected_line_range(), (5, 6));
+    /// tree.toggle_current_node_highlight();
+    /// assert_eq!(tree.selected_line_range(), (5, 5));
+    /// ```
+    pub fn selected_line_range(&self) -> (usize, usize) {
+        let first = self.find_current_line();
+        if !self.highlight {
+            return (first, first);
+        }
+
+        let own_lines = match self.key_to_node(self.current_node).parent {
+            Some(parent) => self.is_expanded(parent),
+            None => true,
+        };
+        let count = match &self.key_to_node(self.current_node).node {
+            NodeType::Terminal(_) if own_lines => self.terminal_line_count(self.current_node),
+            _ => 1,
+        };
+
+        (first, first + count)
+    }
+
+    /// Column and lines, as a half-open range, of the indent guide marking the scope of the
+    /// selected container, drawn brighter than the others. `None` unless guides are shown and
+    /// the selection is an expanded container.
+    pub fn active_guide(&self) -> Option<(usize, (usize, usize))> {
+        let width = self.indent.width();
+        if !self.indent_guides || width == 0 || !self.is_expanded(self.current_node) {
+            return None;
+        }
+
+        let mut depth = 0;
+        let mut parent = self.key_to_node(self.current_node).parent;
+        while let Some(key) = parent {
+            depth += 1;
+            parent = self.key_to_node(key).parent;
+        }
+
+        // between the brackets
+        let first = self.find_current_line();
+        let last = first + self.line_count(self.current_node) - 1;
+        Some((depth * width, (first + 1, last)))
+    }
+
+    /// Returns the node drawn on `line` of [`Tree::to_text`], the inverse of
+    /// [`Tree::find_current_line`]. The closing bracket of a container belongs to the container,
+    /// and a container drawn on one line to itself rather than its children.
+    ///
+    /// ```
+    /// use json_tui::Tree;
+    ///
+    /// let mut tree = Tree::try_from_str(r#"{"a": [1, 2], "b": 3}"#).unwrap();
+    /// let b = tree.node_at_line(5).unwrap();
+    /// tree.select(b);
+    /// assert_eq!(tree.find_current_line(), 5);
+    /// assert_eq!(tree.node_at_line(4), tree.node_at_line(1));
+    /// assert_eq!(tree.node_at_line(7), None);
+    /// ```
+    pub fn node_at_line(&self, line: usize) -> Option<DefaultKey> {
+        if line >= self.line_count(self.root) {
+            return None;
+        }
+
+        let mut key = self.root;
+        let mut start = 0;
+
+        'descend: loop {
+            if line == start || !self.is_expanded(key) {
+                return Some(key);
+            }
+
+            let NodeType::NonTerminal(v) = &self.key_to_node(key).node else {
+                unreachable!("only containers are expanded");
+            };
+
+            // past the opening bracket, skip the children ending before `line`
+            start += 1;
+            for child in v.node.children(self.sort_keys) {
+                let count = self.line_count(child);
+                if line < start + count {
+                    key = child;
+                    continue 'descend;
+                }
+                start += count;
+            }
+
+            // the closing bracket
+            return Some(key);
+        }
+    }
+
+    /// Number of lines `key` takes in [`Tree::to_text`], which is what line lookups are based on.
+    pub fn line_count(&self, key: DefaultKey) -> usize {
+        if let Some(counts) = &*self.line_counts.borrow() {
+            return counts[key];
+        }
+
+        let counts = self.count_lines();
+        let count = counts[key];
+        *self.line_counts.borrow_mut() = Some(counts);
+        count
+    }
+
+    /// Counts the lines of every node at once, children before their parent, so that looking
+    /// lines up in deeply nested documents does not count the same nodes again at each level.
+    fn count_lines(&self) -> SecondaryMap<DefaultKey, usize> {
+        let mut counts = SecondaryMap::with_capacity(self.slot_map.len());
+        let mut stack = vec![(self.root, false)];
+
+        while let Some((key, children_done)) = stack.pop() {
+            let count = match &self.key_to_node(key).node {
+                NodeType::Terminal(_) => self.terminal_line_count(key),
+                NodeType::NonTerminal(v) if !children_done => {
+                    stack.push((key, true));
+                    stack.extend(v.node.children(false).into_iter().map(|k| (k, false)));
+                    continue;
+                }
+                // the brackets stand on their own lines, around those of the children
+                NodeType::NonTerminal(v) if self.is_expanded(key) => {
+                    2 + v
+                        .node
+                        .children(false)
+                        .iter()
+                        .map(|k| counts[*k])
+                        .sum::<usize>()
+                }
+                NodeType::NonTerminal(_) => 1,
             };
-            let parent_key = slot_map.insert(node);
+            counts.insert(key, count);
+        }
 
-            let mut vec = vec![];
+        counts
+    }
 
-            for v in array {
-                let key = value_to_key(v, slot_map, Some(parent_key));
-                vec.push(key);
+    /// Whether `key` is a container drawn over several lines, one per child.
+    fn is_expanded(&self, key: DefaultKey) -> bool {
+        match &self.key_to_node(key).node {
+            NodeType::Terminal(_) => false,
+            NodeType::NonTerminal(v) => {
+                v.is_visible() && !v.node.is_empty() && !self.is_inline(key)
             }
+        }
+    }
 
-            // we know all this is safe by construction
-            let node = slot_map.get_mut(parent_key).unwrap();
+    fn node_render_steps(&self, key: DefaultKey, indent_level: usize) -> Vec<RenderStep> {
+        let node = self.key_to_node(key);
+        let styled = |text: String, style: Style| RenderStep::Raw { text, style };
+        let punctuation = |text: &str| styled(text.to_string(), self.theme.punctuation_style());
+        // the node's own value carries the search match background, not its children
+        let own = |style: Style| {
+            let style = match node.status() {
+                Some(status) => self.theme.diff(style, status),
+                None => style,
+            };
+            if node.matched {
+                self.theme.search_match(style)
+            } else {
+                style
+            }
+        };
+
+        match &node.node {
+            NodeType::Terminal(v @ Value::String(s)) => {
+                // cut before escaping, so that no escape sequence is split
+                let (shown, hidden) = self.shown_string(node, s);
+                let text = if self.multiline_strings {
+                    let lines: Vec<String> = shown
+                        .split('\n')
+                        .map(|line| self.quotes.escape(line))
+                        .collect();
+                    lines.join("\n")
+                } else {
+                    self.quotes.escape(shown)
+                };
+                let style = own(self.theme.value_style(v));
+                let quote = self.quotes.quote();
+
+                if hidden == 0 {
+                    return vec![styled(format!("{quote}{text}{quote}"), style)];
+                }
+
+                vec![
+                    styled(format!("{quote}{text}"), style),
+                    styled(
+                        format!("… (+{} chars)", group_thousands(&hidden.to_string())),
+                        own(self.theme.muted_style()),
+                    ),
+                    styled(quote.to_string(), style),
+                ]
+            }
+            NodeType::Terminal(v @ Value::Number(_)) if self.group_digits => {
+                let text = node.terminal_json();
+                let digits = text.strip_prefix('-').unwrap_or(&text);
+                let text = if digits.bytes().all(|b| b.is_ascii_digit()) {
+                    format!(
+                        "{}{}",
+                        &text[..text.len() - digits.len()],
+                        group_thousands(digits)
+                    )
+                } else {
+                    text
+                };
+                vec![styled(text, own(self.theme.value_style(v)))]
+            }
+            NodeType::Terminal(v) => vec![styled(
+                node.terminal_text(self.quotes),
+                own(self.theme.value_style(v)),
+            )],
+            NodeType::NonTerminal(v) if !v.is_visible() => {
+                // same text as HidableValue::summary or Tree::preview, with the contents dimmed
+                let (open, close) = v.brackets();
+                let contents = if self.collapsed_preview && !v.node.is_empty() {
+                    let name_width = self
+                        .key_name(key)
+                        .map_or(0, |n| self.quotes.quoted(n).width() + 2);
+                    let width = self
+                        .view_width
+                        .saturating_sub(self.indent.width() * indent_level + name_width + 1);
+                    let preview = self.preview(key, width);
+                    preview[open.len()..preview.len() - close.len()].to_string()
+                } else {
+                    format!("… {}", v.size_label())
+                };
+                vec![
+                    styled(open.to_string(), own(self.theme.punctuation_style())),
+                    styled(contents, own(self.theme.muted_style())),
+                    styled(close.to_string(), own(self.theme.punctuation_style())),
+                ]
+            }
+            NodeType::NonTerminal(v) => {
+                let (open, close) = v.brackets();
+                let children = v.node.entries(self.sort_keys);
+
+                let open_style = own(self.theme.punctuation_style());
+
+                if children.is_empty() {
+                    return vec![styled(format!("{open}{close}"), open_style)];
+                }
+
+                let mut steps = vec![styled(open.to_string(), open_style)];
+
+                if self.is_inline(key) {
+                    for (i, (name, child)) in children.iter().enumerate() {
+                        if i > 0 {
+                            steps.push(punctuation(", "));
+                        }
+                        if let Some(name) = name {
+                            steps.push(styled(self.quotes.quoted(name), self.name_style(*child)));
+                            steps.push(punctuation(": "));
+                        }
+                        steps.push(RenderStep::Node {
+                            key: *child,
+                            indent_level,
+                        });
+                    }
+
+                    steps.push(punctuation(close));
+                    return steps;
+                }
+
+                for (i, (name, child)) in children.iter().enumerate() {
+                    steps.push(RenderStep::NewLine);
+                    steps.extend(self.indent_step(indent_level + 1));
+                    if let Some(name) = name {
+                        steps.push(styled(self.quotes.quoted(name), self.name_style(*child)));
+                        steps.push(punctuation(": "));
+                    }
+                    steps.push(RenderStep::Node {
+                        key: *child,
+                        indent_level: indent_level + 1,
+                    });
+
+                    if i < children.len() - 1 {
+                        steps.push(punctuation(","));
+                    }
 
-            match &mut node.node {
-                NodeType::NonTerminal(hv) => match &mut hv.node {
-                    NonTerminalNode::Array(v) => {
-                        let _ = std::mem::replace(v, vec);
+                    if self.timestamps
+                        && let NodeType::Terminal(v) = &self.key_to_node(*child).node
+                        && let Some(date) = timestamp::annotation(*name, v)
+                    {
+                        steps.push(styled(format!(" // {date}"), self.theme.muted_style()));
                     }
+                }
+
+                steps.push(RenderStep::NewLine);
+                steps.extend(self.indent_step(indent_level));
+                steps.push(punctuation(close));
+                steps
+            }
+        }
+    }
+
+    /// The part of string `s` shown for `node`, and the number of characters cut from it.
+    fn shown_string<'a>(&self, node: &Node, s: &'a str) -> (&'a str, usize) {
+        if node.expanded || self.string_limit == 0 {
+            return (s, 0);
+        }
+
+        match s.char_indices().nth(self.string_limit) {
+            Some((i, _)) => (&s[..i], s[i..].chars().count()),
+            None => (s, 0),
+        }
+    }
+
+    /// Number of lines taken by a terminal, more than one for multi-line strings.
+    fn terminal_line_count(&self, key: DefaultKey) -> usize {
+        let node = self.key_to_node(key);
+
+        match &node.node {
+            NodeType::Terminal(Value::String(s)) if self.multiline_strings => {
+                1 + self.shown_string(node, s).0.matches('\n').count()
+            }
+            _ => 1,
+        }
+    }
+
+    /// Style of the key under which `key` is stored, coloured like its value in a comparison.
+    fn name_style(&self, key: DefaultKey) -> Style {
+        match self.key_to_node(key).status() {
+            Some(status) => self.theme.diff(self.theme.key_style(), status),
+            None => self.theme.key_style(),
+        }
+    }
+
+    /// Indentation of `stops` levels, drawn with a guide at each stop when enabled. It is a
+    /// single span however deep the line, so that deep nesting does not multiply the spans.
+    fn indent_step(&self, stops: usize) -> Option<RenderStep> {
+        let width = self.indent.width();
+        if stops == 0 || width == 0 {
+            return None;
+        }
+
+        let (text, style) = if self.indent_guides {
+            let guide = format!("│{}", " ".repeat(width - 1));
+            (guide.repeat(stops), self.theme.muted_style())
+        } else {
+            (" ".repeat(width * stops), Style::default())
+        };
+        Some(RenderStep::Raw { text, style })
+    }
+}
+
+enum RenderStep {
+    Node {
+        key: DefaultKey,
+        indent_level: usize,
+    },
+    Raw {
+        text: String,
+        style: Style,
+    },
+    NewLine,
+}
+
+/// Name of the JSON type of `v`, such as `string` or `object`.
+pub fn value_type(v: &Value) -> &'static str {
+    match v {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// Whether `n` is written with a fraction or an exponent, as `3.0` or `1e3`, rather than as an
+/// integer. Going by the text keeps this true of numbers no `f64` can hold:
+///
+/// ```
+/// use json_tui::node::is_float;
+/// use serde_json::Number;
+///
+/// let number = |s: &str| s.parse::<Number>().unwrap();
+/// assert!(is_float(&number("3.0")) && is_float(&number("1e400")));
+/// assert!(!is_float(&number("3")) && !is_float(&number("-123456789012345678901234567890")));
+/// ```
+pub fn is_float(n: &Number) -> bool {
+    n.as_str().contains(['.', 'e', 'E'])
+}
+
+/// A terminal written as JSON, a number as `written` when it was read so.
+fn terminal_json(value: &Value, written: Option<&str>) -> String {
+    match (value, written) {
+        (Value::Number(_), Some(written)) => written.to_string(),
+        (Value::Number(n), None) => n.as_str().to_string(),
+        _ => value.to_string(),
+    }
+}
+
+/// Like [`Node::terminal_text`], cut to about `max_width` columns without copying the whole
+/// value.
+fn truncated_text(
+    value: &Value,
+    written: Option<&str>,
+    max_width: usize,
+    quotes: QuoteStyle,
+) -> String {
+    match value {
+        Value::String(s) => truncated_quoted(s, max_width, quotes),
+        _ => {
+            let text = terminal_json(value, written);
+            if text.width() > max_width {
+                let mut cut = start_of_width(&text, max_width.saturating_sub(1)).to_string();
+                cut.push('…');
+                cut
+            } else {
+                text
+            }
+        }
+    }
+}
+
+/// Writes `digits` with a `,` between groups of three, e.g. `183,402`.
+fn group_thousands(digits: &str) -> String {
+    let mut grouped = String::new();
+
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(c);
+    }
+
+    grouped
+}
+
+fn brackets(array: bool) -> (&'static str, &'static str) {
+    if array { ("[", "]") } else { ("{", "}") }
+}
+
+/// Quotes the first characters of `s`, marking with `…` that the rest was left out.
+fn truncated_quoted(s: &str, max_width: usize, quotes: QuoteStyle) -> String {
+    let quote = quotes.quote();
+    if s.width() <= max_width.saturating_sub(2 * quote.len()) {
+        return quotes.quoted(s);
+    }
+
+    let start = start_of_width(s, max_width.saturating_sub(2 * quote.len() + 1));
+    format!("{quote}{}…{quote}", quotes.escape(start))
+}
+
+/// The longest start of `s` taking at most `width` columns.
+fn start_of_width(s: &str, width: usize) -> &str {
+    let mut taken = 0;
+
+    for (i, c) in s.char_indices() {
+        taken += c.width().unwrap_or(0);
+        if taken > width {
+            return &s[..i];
+        }
+    }
+
+    s
+}
+
+/// A node holding `value`, collapsed when it is a container, its children being left in the
+/// value until [`Tree::build`] when it has any.
+fn unbuilt_node(value: Value, parent: Option<DefaultKey>) -> Node {
+    let collapsed = |node| {
+        NodeType::NonTerminal(HidableValue {
+            visible: false,
+            node,
+        })
+    };
+    let node = match value {
+        Value::Array(arr) if arr.is_empty() => collapsed(NonTerminalNode::Array(vec![])),
+        Value::Object(obj) if obj.is_empty() => collapsed(NonTerminalNode::Object(vec![])),
+        v @ (Value::Array(_) | Value::Object(_)) => collapsed(NonTerminalNode::Unbuilt(v)),
+        v => NodeType::Terminal(v),
+    };
+
+    Node {
+        parent,
+        matched: false,
+        diff: None,
+        modified: false,
+        expanded: false,
+        span: None,
+        written: None,
+        node,
+    }
+}
+
+/// Inserts `value` and all of its descendants in `slot_map`, returning the key of `value`.
+pub fn value_to_key(
+    value: Value,
+    slot_map: &mut SlotMap<DefaultKey, Node>,
+    parent: Option<DefaultKey>,
+) -> DefaultKey {
+    let mut root_key = None;
+
+    // built with an explicit stack so that pathologically deep input cannot overflow
+    let mut stack: Vec<(Value, Option<DefaultKey>, Option<String>)> = vec![(value, parent, None)];
+
+    while let Some((value, parent, name)) = stack.pop() {
+        let (node, children) = match value {
+            v @ (Value::Null | Value::String(_) | Value::Number(_) | Value::Bool(_)) => {
+                (NodeType::Terminal(v), vec![])
+            }
+            Value::Object(map) => (
+                NodeType::NonTerminal(HidableValue {
+                    visible: true,
+                    node: NonTerminalNode::Object(vec![]),
+                }),
+                map.into_iter().map(|(k, v)| (Some(k), v)).collect(),
+            ),
+            Value::Array(array) => (
+                NodeType::NonTerminal(HidableValue {
+                    visible: true,
+                    node: NonTerminalNode::Array(vec![]),
+                }),
+                array.into_iter().map(|v| (None, v)).collect::<Vec<_>>(),
+            ),
+        };
+
+        let key = slot_map.insert(Node {
+            parent,
+            matched: false,
+            diff: None,
+            modified: false,
+            expanded: false,
+            span: None,
+            written: None,
+            node,
+        });
+
+        if root_key.is_none() {
+            root_key = Some(key);
+        } else {
+            // we know all this is safe by construction
+            let parent_node = slot_map.get_mut(parent.unwrap()).unwrap();
+
+            match &mut parent_node.node {
+                NodeType::NonTerminal(hv) => match (&mut hv.node, name) {
+                    (NonTerminalNode::Array(v), None) => v.push(key),
+                    (NonTerminalNode::Object(v), Some(name)) => v.push((name, key)),
                     _ => unreachable!(),
                 },
                 _ => unreachable!(),
             }
-            parent_key
         }
+
+        for (name, child) in children.into_iter().rev() {
+            stack.push((child, Some(key), name));
+        }
+    }
+
+    root_key.unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::path::parse_path;
+
+    fn lines(tree: &Tree) -> Vec<String> {
+        tree.to_text().lines.iter().map(|l| l.to_string()).collect()
+    }
+
+    /// Checks that a document made of the scalar `value` renders as `line` and that moving
+    /// around it leaves the selection on the root.
+    fn navigate_scalar_root(value: Value, line: &str) {
+        let mut tree = Tree::from_value(value);
+        assert_eq!(lines(&tree), [line]);
+
+        assert_eq!(tree.next_node_down(), None);
+        assert_eq!(tree.next_node_up(), None);
+        tree.toggle_current_node_visibility();
+        assert_eq!(tree.current_key(), tree.root);
+        assert_eq!(tree.find_current_line(), 0);
+    }
+
+    #[test]
+    fn number_root() {
+        navigate_scalar_root(json!(42), "42");
+    }
+
+    #[test]
+    fn string_root() {
+        navigate_scalar_root(json!("x"), "\"x\"");
+    }
+
+    #[test]
+    fn bool_root() {
+        navigate_scalar_root(json!(true), "true");
+    }
+
+    #[test]
+    fn null_renders_as_null() {
+        let tree = Tree::from_value(json!(null));
+        assert_eq!(lines(&tree), ["null"]);
+
+        let tree = Tree::from_value(json!({"a": null, "b": [null, "null"]}));
+        assert_eq!(
+            lines(&tree),
+            [
+                "{",
+                "│ \"a\": null,",
+                "│ \"b\": [",
+                "│ │ null,",
+                "│ │ \"null\"",
+                "│ ]",
+                "}"
+            ]
+        );
+
+        // styled apart from the string "null"
+        let text = tree.to_text();
+        let style_of = |line: usize, value: &str| {
+            let spans = &text.lines[line].spans;
+            spans.iter().find(|s| s.content == value).unwrap().style
+        };
+        assert_ne!(style_of(3, "null"), style_of(4, "\"null\""));
+    }
+
+    #[test]
+    fn empty_containers() {
+        let mut tree = Tree::from_value(json!({"a": {}, "b": [], "c": [[]]}));
+        assert_eq!(
+            lines(&tree),
+            [
+                "{",
+                "│ \"a\": {},",
+                "│ \"b\": [],",
+                "│ \"c\": [",
+                "│ │ []",
+                "│ ]",
+                "}"
+            ]
+        );
+
+        let mut down = vec![tree.current_key()];
+        while let Some(key) = tree.next_node_down() {
+            down.push(key);
+        }
+        let paths: Vec<_> = down.iter().map(|&key| tree.path_of(key)).collect();
+        let key = |k: &str| PathSegment::Key(k.to_string());
+        assert_eq!(
+            paths,
+            [
+                vec![],
+                vec![key("a")],
+                vec![key("b")],
+                vec![key("c")],
+                vec![key("c"), PathSegment::Index(0)]
+            ]
+        );
+        let lines: Vec<_> = down.iter().map(|&key| tree.line_of(key)).collect();
+        assert_eq!(lines, [0, 1, 2, 3, 4]);
+
+        let mut up = vec![tree.current_key()];
+        while let Some(key) = tree.next_node_up() {
+            up.push(key);
+        }
+        up.reverse();
+        assert_eq!(up, down);
+    }
+
+    #[test]
+    fn strings_are_escaped_on_one_line() {
+        let mut tree = Tree::from_value(json!({
+            "ctrl": "tab\there\u{1}",
+            "lines": "one\ntwo",
+            "say \"k\"": "a \"b\" \\ c",
+            "wide": "漢字 🎉"
+        }));
+        let text = lines(&tree);
+        assert_eq!(
+            text,
+            [
+                "{",
+                r#"│ "ctrl": "tab\there\u0001","#,
+                r#"│ "lines": "one\ntwo","#,
+                r#"│ "say \"k\"": "a \"b\" \\ c","#,
+                r#"│ "wide": "漢字 🎉""#,
+                "}",
+            ]
+        );
+
+        // every entry is found on the line it is drawn on
+        let mut line = 0;
+        while let Some(key) = tree.next_node_down() {
+            line += 1;
+            assert_eq!(tree.find_current_line(), line);
+            let quoted = serde_json::to_string(tree.key_name(key).unwrap()).unwrap();
+            assert!(text[line].contains(&quoted));
+        }
+        assert_eq!(line, 4);
+        assert_eq!(tree.line_count(tree.root), text.len());
+
+        // the entries past a string breaking over two lines are found a line further down
+        tree.toggle_multiline_strings();
+        let text = lines(&tree);
+        assert_eq!(
+            text[2..],
+            [
+                r#"│ "lines": "one"#,
+                r#"           two","#,
+                r#"│ "say \"k\"": "a \"b\" \\ c","#,
+                r#"│ "wide": "漢字 🎉""#,
+                "}"
+            ]
+        );
+        assert_eq!(tree.line_count(tree.root), text.len());
+        assert_eq!(tree.find_current_line(), 5);
+        tree.next_node_up();
+        assert_eq!(tree.find_current_line(), 4);
+        // both lines of the string lead back to it
+        let lines_key = tree.next_node_up().unwrap();
+        assert_eq!(tree.node_at_line(2), Some(lines_key));
+        assert_eq!(tree.node_at_line(3), Some(lines_key));
+    }
+
+    #[test]
+    fn containers_are_built_once_expanded() {
+        let text = r#"{"a": {"b": [1, 2.50], "c": "x"}, "d": [{"e": null}], "f": [1E3], "g": []}"#;
+        let value = source::read_value(text).unwrap();
+        let mut eager = Tree::from_value(value.clone());
+        source::record_spans(&mut eager, text, false);
+        eager.expand_to_depth(1);
+        let mut lazy = Tree::from_value_to_depth(value, 1);
+        source::record_spans(&mut lazy, text, false);
+
+        // the root and its children, and "f" built to keep how 1E3 was written
+        assert_eq!(lazy.slot_map.len(), 6);
+        for preview in [true, false] {
+            eager.set_collapsed_preview(preview);
+            lazy.set_collapsed_preview(preview);
+            assert_eq!(lines(&lazy), lines(&eager));
+        }
+        let root = lazy.root();
+        assert_eq!(lazy.to_value(root), eager.to_value(eager.root()));
+        assert_eq!(
+            lazy.to_json(root, Indent::Tab),
+            eager.to_json(eager.root(), Indent::Tab)
+        );
+        assert_eq!(lazy.to_gron(root), eager.to_gron(eager.root()));
+        assert_eq!(lazy.stats(), eager.stats());
+        assert_eq!(
+            lazy.compact_preview(root, 100),
+            eager.compact_preview(eager.root(), 100)
+        );
+
+        lazy.select(lazy.node_at_line(1).unwrap());
+        lazy.toggle_current_node_visibility();
+        assert_eq!(lines(&lazy)[2], "│ │ \"b\": [… 2 items],");
+
+        // everything built, spans included, once everything is shown
+        eager.expand_to_depth(usize::MAX);
+        lazy.expand_to_depth(usize::MAX);
+        assert_eq!(lines(&lazy), lines(&eager));
+        for line in 0..lines(&eager).len() {
+            let (l, e) = (lazy.node_at_line(line), eager.node_at_line(line));
+            assert_eq!(lazy.span(l.unwrap()), eager.span(e.unwrap()));
+        }
+    }
+
+    #[test]
+    fn searching_builds_collapsed_containers() {
+        let mut tree = Tree::from_value_to_depth(json!({"a": [{"b": "needle"}], "c": 1}), 1);
+        assert_eq!(tree.find_matches("needle", false), vec![]);
+
+        let found = tree.find_matches("needle", true);
+        assert_eq!(found.len(), 1);
+        assert_eq!(tree.path_of(found[0]), parse_path("a[0].b").unwrap());
+        tree.select(found[0]);
+        assert_eq!(tree.find_current_line(), 3);
+
+        let mut tree = Tree::from_value_to_depth(json!({"a": [{"b": 1}]}), 0);
+        let b = tree.find_path(&parse_path("a[0].b").unwrap()).unwrap();
+        assert_eq!(tree.to_value(b), json!(1));
+    }
+
+    #[test]
+    fn inserted_nodes_go_last_in_the_selected_container() {
+        let mut tree = Tree::try_from_str(r#"{"a": [1, 3], "c": [5]}"#).unwrap();
+        let root = tree.root();
+        let written = |tree: &Tree| serde_json::to_string(&tree.to_value(root)).unwrap();
+        let select = |tree: &mut Tree, path: &str| {
+            let key = tree.find_path(&parse_path(path).unwrap()).unwrap();
+            tree.select(key);
+        };
+
+        tree.insert(Some("b".to_string()), json!([])).unwrap();
+        assert_eq!(written(&tree), r#"{"a":[1,3],"c":[5],"b":[]}"#);
+
+        // into the expanded array, the empty one, and after the selected item
+        select(&mut tree, "a");
+        tree.insert(None, json!(4)).unwrap();
+        select(&mut tree, "b");
+        tree.insert(None, json!(6)).unwrap();
+        select(&mut tree, "a[0]");
+        tree.insert(None, json!(2)).unwrap();
+        assert_eq!(written(&tree), r#"{"a":[1,2,3,4],"c":[5],"b":[6]}"#);
+
+        // next to a collapsed container rather than into it
+        let c = tree.find_path(&parse_path("c").unwrap()).unwrap();
+        tree.set_collapsed(c, true);
+        tree.select(c);
+        tree.insert(Some("d".to_string()), json!(null)).unwrap();
+        assert_eq!(
+            written(&tree),
+            r#"{"a":[1,2,3,4],"c":[5],"d":null,"b":[6]}"#
+        );
+
+        for _ in 0..5 {
+            tree.undo();
+        }
+        assert_eq!(written(&tree), r#"{"a":[1,3],"c":[5]}"#);
+    }
+
+    #[test]
+    fn numbers_are_kept_as_written() {
+        let text = r#"{"big": 9007199254740993, "e": [1e3, 2.5E-7, 1E+2], "zero": -0.0}"#;
+        let tree = Tree::try_from_str(text).unwrap();
+        assert_eq!(
+            lines(&tree)[3..7],
+            ["│ │ 1e3,", "│ │ 2.5E-7,", "│ │ 1E+2", "│ ],"]
+        );
+        assert_eq!(lines(&tree)[1], "│ \"big\": 9007199254740993,");
+        assert_eq!(lines(&tree)[7], "│ \"zero\": -0.0");
+
+        let mut compact = Tree::try_from_str(text).unwrap();
+        compact.set_compact(true);
+        assert_eq!(lines(&compact)[2], "│ \"e\": [1e3, 2.5E-7, 1E+2],");
+
+        let json = tree.to_json(tree.root(), Indent::Spaces(2));
+        assert!(json.contains("    1e3,\n    2.5E-7,\n    1E+2\n") && json.contains("-0.0"));
+        assert_eq!(
+            source::read_value(&json).unwrap(),
+            tree.to_value(tree.root())
+        );
+    }
+
+    #[test]
+    fn json_is_written_as_serde_json_writes_it() {
+        let value = json!({
+            "a": [1, "two", null, true, {"b": [], "c": {}}],
+            "say \"hi\"": "tab\t, 漢字 and \u{1}",
+            "d": {"e": [[1.5]]}
+        });
+        let mut tree = Tree::from_value(value.clone());
+        let root = tree.root();
+        assert_eq!(
+            tree.to_json(root, Indent::Spaces(2)),
+            serde_json::to_string_pretty(&value).unwrap()
+        );
+        let scalar = tree.find_path(&[PathSegment::Key("a".to_string()), PathSegment::Index(1)]);
+        assert_eq!(tree.to_json(scalar.unwrap(), Indent::Tab), "\"two\"");
+        assert!(tree.to_json(root, Indent::Tab).contains("\n\t\t1,\n"));
+    }
+
+    #[test]
+    fn deeply_nested_input() {
+        const DEPTH: usize = 10_000;
+        let text = format!("{}{}", "[".repeat(DEPTH), "]".repeat(DEPTH));
+        let mut tree = Tree::try_from_str(&text).unwrap();
+        // the indentation alone would take hundreds of megabytes at this depth
+        tree.set_indent(Indent::Spaces(0));
+
+        let rendered = tree.to_text();
+        assert_eq!(rendered.lines.len(), 2 * DEPTH - 1);
+        assert_eq!(rendered.lines[DEPTH - 1].to_string(), "[]");
+
+        while tree.next_node_down().is_some() {}
+        let innermost = tree.current_key();
+        assert_eq!(tree.line_of(innermost), DEPTH - 1);
+        assert_eq!(tree.node_at_line(DEPTH - 1), Some(innermost));
+        assert_eq!(tree.node_at_line(2 * DEPTH - 2), Some(tree.root()));
     }
 }
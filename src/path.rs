@@ -0,0 +1,257 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+/// Formats a path jq-style, e.g. `.data.items[5].name`, with `.` standing for the root. Keys that
+/// are not simple identifiers are quoted in brackets, with JSON escaping, and a path starting
+/// with brackets keeps the leading `.` that jq needs:
+///
+/// ```
+/// use json_tui::path::{PathSegment, PathStyle, format_path};
+///
+/// let key = |k: &str| PathSegment::Key(k.to_string());
+/// assert_eq!(format_path(&[key("a.b"), PathSegment::Index(0)]), r#".["a.b"][0]"#);
+/// assert_eq!(format_path(&[PathSegment::Index(0)]), ".[0]");
+/// assert_eq!(format_path(&[key("has space")]), r#".["has space"]"#);
+/// assert_eq!(format_path(&[key("with\"quote"), key("_id")]), r#".["with\"quote"]._id"#);
+/// assert_eq!(PathStyle::Jq.format(&[key("a"), key("b c")]), r#".a["b c"]"#);
+/// ```
+pub fn format_path(path: &[PathSegment]) -> String {
+    let formatted: String = path
+        .iter()
+        .map(|segment| match segment {
+            PathSegment::Key(k) if is_identifier(k) => format!(".{k}"),
+            // serializing a str cannot fail
+            PathSegment::Key(k) => format!("[{}]", serde_json::to_string(k).unwrap()),
+            PathSegment::Index(i) => format!("[{i}]"),
+        })
+        .collect();
+
+    // the root and paths starting with brackets are led by the `.` of the root
+    if formatted.starts_with('.') {
+        formatted
+    } else {
+        format!(".{formatted}")
+    }
+}
+
+/// How a path is written when copied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PathStyle {
+    /// As by [`format_path`].
+    #[default]
+    Jq,
+    /// As by [`format_pointer`].
+    Pointer,
+    /// As by [`format_js_path`].
+    JavaScript,
+    /// As by [`format_bracket_path`].
+    Bracket,
+}
+
+impl PathStyle {
+    pub fn format(self, path: &[PathSegment]) -> String {
+        match self {
+            Self::Jq => format_path(path),
+            Self::Pointer => format_pointer(path),
+            Self::JavaScript => format_js_path(path),
+            Self::Bracket => format_bracket_path(path),
+        }
+    }
+
+    /// The style after this one, cycling back to the first.
+    pub fn next(self) -> Self {
+        match self {
+            Self::Jq => Self::Pointer,
+            Self::Pointer => Self::JavaScript,
+            Self::JavaScript => Self::Bracket,
+            Self::Bracket => Self::Jq,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Jq => "jq path",
+            Self::Pointer => "JSON pointer",
+            Self::JavaScript => "JavaScript path",
+            Self::Bracket => "bracket path",
+        }
+    }
+}
+
+/// Formats a path as a JSON pointer (RFC 6901), e.g. `/data/items/5/name`, the root being the
+/// empty string. `~` and `/` in keys are escaped as `~0` and `~1`:
+///
+/// ```
+/// use json_tui::path::{PathSegment, format_pointer};
+///
+/// let key = |k: &str| PathSegment::Key(k.to_string());
+/// assert_eq!(format_pointer(&[key("a/b"), PathSegment::Index(0), key("~x")]), "/a~1b/0/~0x");
+/// assert_eq!(format_pointer(&[key("a.b c")]), "/a.b c");
+/// assert_eq!(format_pointer(&[]), "");
+/// ```
+pub fn format_pointer(path: &[PathSegment]) -> String {
+    path.iter()
+        .map(|segment| match segment {
+            PathSegment::Key(k) => format!("/{}", k.replace('~', "~0").replace('/', "~1")),
+            PathSegment::Index(i) => format!("/{i}"),
+        })
+        .collect()
+}
+
+/// Formats a path as JavaScript property accesses, e.g. `data.items[5].name`, to follow the
+/// variable holding the document. Keys that are not identifiers are quoted in brackets:
+///
+/// ```
+/// use json_tui::path::{PathSegment, format_js_path};
+///
+/// let key = |k: &str| PathSegment::Key(k.to_string());
+/// assert_eq!(format_js_path(&[key("$ref"), PathSegment::Index(0)]), "$ref[0]");
+/// assert_eq!(format_js_path(&[key("a"), key("b.c"), key("it's")]), r#"a["b.c"]["it's"]"#);
+/// assert_eq!(format_js_path(&[PathSegment::Index(1), key("x")]), "[1].x");
+/// ```
+pub fn format_js_path(path: &[PathSegment]) -> String {
+    let mut formatted = String::new();
+
+    for segment in path {
+        match segment {
+            PathSegment::Key(k) if is_js_identifier(k) => {
+                if !formatted.is_empty() {
+                    formatted.push('.');
+                }
+                formatted.push_str(k);
+            }
+            // JSON strings are valid JavaScript literals
+            PathSegment::Key(k) => {
+                formatted.push_str(&format!("[{}]", serde_json::to_string(k).unwrap()));
+            }
+            PathSegment::Index(i) => formatted.push_str(&format!("[{i}]")),
+        }
+    }
+
+    formatted
+}
+
+/// Formats a path with every step in brackets, e.g. `["data"]["items"][5]["name"]`, to follow
+/// a variable in most languages, keys being written as JSON strings:
+///
+/// ```
+/// use json_tui::path::{PathSegment, format_bracket_path};
+///
+/// let key = |k: &str| PathSegment::Key(k.to_string());
+/// assert_eq!(format_bracket_path(&[key("data"), PathSegment::Index(0)]), r#"["data"][0]"#);
+/// assert_eq!(format_bracket_path(&[key("say \"hi\"")]), r#"["say \"hi\""]"#);
+/// assert_eq!(format_bracket_path(&[]), "");
+/// ```
+pub fn format_bracket_path(path: &[PathSegment]) -> String {
+    path.iter()
+        .map(|segment| match segment {
+            PathSegment::Key(k) => format!("[{}]", serde_json::to_string(k).unwrap()),
+            PathSegment::Index(i) => format!("[{i}]"),
+        })
+        .collect()
+}
+
+/// Formats a path as [gron](https://github.com/tomnomnom/gron) does, as JavaScript property
+/// accesses of the variable `json`:
+///
+/// ```
+/// use json_tui::path::{PathSegment, format_gron_path};
+///
+/// let key = |k: &str| PathSegment::Key(k.to_string());
+/// assert_eq!(format_gron_path(&[key("users"), PathSegment::Index(0)]), "json.users[0]");
+/// assert_eq!(format_gron_path(&[PathSegment::Index(1), key("a b")]), r#"json[1]["a b"]"#);
+/// assert_eq!(format_gron_path(&[]), "json");
+/// ```
+pub fn format_gron_path(path: &[PathSegment]) -> String {
+    let accesses = format_js_path(path);
+    if accesses.is_empty() || accesses.starts_with('[') {
+        format!("json{accesses}")
+    } else {
+        format!("json.{accesses}")
+    }
+}
+
+fn is_js_identifier(key: &str) -> bool {
+    let mut chars = key.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '$')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+}
+
+fn is_identifier(key: &str) -> bool {
+    let mut chars = key.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Parses a path written as by [`format_path`], the leading `.` being optional. Keys given after a
+/// `.` run until the next `.` or `[`, so they need no quoting unless they contain one of those.
+///
+/// ```
+/// use json_tui::path::{PathSegment, parse_path};
+///
+/// let path = parse_path(r#"data.items[5]["a.b"]"#).unwrap();
+/// assert_eq!(path[1], PathSegment::Key("items".to_string()));
+/// assert_eq!(path[2], PathSegment::Index(5));
+/// assert_eq!(path[3], PathSegment::Key("a.b".to_string()));
+/// assert_eq!(parse_path("."), Some(vec![]));
+/// assert_eq!(parse_path(".[0]"), parse_path("[0]"));
+/// assert_eq!(parse_path("a[x]"), None);
+/// ```
+pub fn parse_path(path: &str) -> Option<Vec<PathSegment>> {
+    let path = match path.trim() {
+        "" | "." => return Some(vec![]),
+        path if path.starts_with(".[") => path[1..].to_string(),
+        path if path.starts_with(['.', '[']) => path.to_string(),
+        path => format!(".{path}"),
+    };
+
+    let mut segments = vec![];
+    let mut rest = path.as_str();
+
+    while !rest.is_empty() {
+        if let Some(after_dot) = rest.strip_prefix('.') {
+            let end = after_dot.find(['.', '[']).unwrap_or(after_dot.len());
+            if end == 0 {
+                return None;
+            }
+            segments.push(PathSegment::Key(after_dot[..end].to_string()));
+            rest = &after_dot[end..];
+        } else if let Some(quoted) = rest.strip_prefix("[\"") {
+            // find the closing quote, skipping escaped characters
+            let mut escaped = false;
+            let end = quoted.char_indices().find_map(|(i, c)| match c {
+                _ if escaped => {
+                    escaped = false;
+                    None
+                }
+                '\\' => {
+                    escaped = true;
+                    None
+                }
+                '"' => Some(i),
+                _ => None,
+            })?;
+            let key = serde_json::from_str(&rest[1..end + 3]).ok()?;
+            segments.push(PathSegment::Key(key));
+            rest = quoted[end + 1..].strip_prefix(']')?;
+        } else {
+            let index = rest.strip_prefix('[')?;
+            let end = index.find(']')?;
+            segments.push(PathSegment::Index(index[..end].parse().ok()?));
+            rest = &index[end + 1..];
+        }
+    }
+
+    Some(segments)
+}
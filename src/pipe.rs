@@ -0,0 +1,157 @@
+use std::{
+    io::{self, Read, Write},
+    process::{Child, Command, ExitStatus, Stdio},
+    thread::JoinHandle,
+};
+
+use ratatui::{
+    style::Style,
+    text::{Line, Text},
+};
+
+/// Output kept from each stream of a command, 1 MiB, the rest being cut off by closing the pipe.
+const OUTPUT_LIMIT: u64 = 1 << 20;
+/// Lines shown of each stream, more making the overlay slow to draw.
+const LINE_LIMIT: usize = 10_000;
+
+/// A shell command the selection is being piped through. Its streams are handled by threads so
+/// that the interface keeps running, and can stop it, however much it reads or writes. Commands
+/// are filters run in the background: the interface keeps the terminal, so interactive ones
+/// such as `less` or `fzf` are not supported.
+pub struct Pipe {
+    pub command: String,
+    child: Child,
+    stdout: JoinHandle<io::Result<Captured>>,
+    stderr: JoinHandle<io::Result<Captured>>,
+}
+
+/// Text read from one stream of a command.
+struct Captured {
+    text: String,
+    truncated: bool,
+}
+
+impl Pipe {
+    /// Starts `command` in the shell with `input` written to its stdin.
+    pub fn spawn(command: String, input: String) -> io::Result<Self> {
+        let mut child = shell(&command)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        std::thread::spawn(move || {
+            // commands are free to stop reading early
+            let _ = stdin.write_all(input.as_bytes());
+        });
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let stderr = child.stderr.take().expect("stderr is piped");
+
+        Ok(Self {
+            command,
+            child,
+            stdout: std::thread::spawn(move || capture(stdout)),
+            stderr: std::thread::spawn(move || capture(stderr)),
+        })
+    }
+
+    /// Whether the command has exited and all it printed has been read.
+    pub fn is_finished(&mut self) -> io::Result<bool> {
+        Ok(self.child.try_wait()?.is_some()
+            && self.stdout.is_finished()
+            && self.stderr.is_finished())
+    }
+
+    /// Returns what the command printed, waiting for it to exit.
+    pub fn finish(mut self) -> io::Result<PipeOutput> {
+        let status = self.child.wait()?;
+        let join = |handle: JoinHandle<io::Result<Captured>>| match handle.join() {
+            Ok(captured) => captured,
+            Err(panic) => std::panic::resume_unwind(panic),
+        };
+
+        Ok(PipeOutput {
+            status,
+            stdout: join(self.stdout)?,
+            stderr: join(self.stderr)?,
+        })
+    }
+
+    pub fn kill(mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// What a finished command printed, and how it exited.
+pub struct PipeOutput {
+    status: ExitStatus,
+    stdout: Captured,
+    stderr: Captured,
+}
+
+impl PipeOutput {
+    /// The output followed by any error output and a failed exit status, dimmed.
+    pub fn to_text(&self, muted: Style) -> Text<'static> {
+        let mut lines: Vec<Line> = self.stdout.lines().map(Line::raw).collect();
+
+        for line in self.stderr.lines() {
+            lines.push(Line::styled(line, muted));
+        }
+        if !self.status.success() {
+            lines.push(Line::styled(self.status.to_string(), muted));
+        }
+        if lines.is_empty() {
+            lines.push(Line::styled("(no output)", muted));
+        }
+
+        lines.into()
+    }
+}
+
+impl Captured {
+    fn lines(&self) -> impl Iterator<Item = String> {
+        let mut lines = self.text.lines();
+        let shown: Vec<String> = lines
+            .by_ref()
+            .take(LINE_LIMIT)
+            .map(str::to_string)
+            .collect();
+        let cut = (self.truncated || lines.next().is_some()).then(|| "… (output cut)".to_string());
+        shown.into_iter().chain(cut)
+    }
+}
+
+/// Reads `stream` up to [`OUTPUT_LIMIT`], then drops it so that a command printing endlessly
+/// fails on its next write rather than filling the memory.
+fn capture(stream: impl Read) -> io::Result<Captured> {
+    let mut bytes = vec![];
+    let read = stream.take(OUTPUT_LIMIT + 1).read_to_end(&mut bytes)?;
+    let truncated = read as u64 > OUTPUT_LIMIT;
+    bytes.truncate(OUTPUT_LIMIT as usize);
+
+    Ok(Captured {
+        text: String::from_utf8_lossy(&bytes).into_owned(),
+        truncated,
+    })
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.args(["/C", command]);
+    shell
+}
+
+#[cfg(not(windows))]
+fn shell(command: &str) -> Command {
+    use std::os::unix::process::CommandExt;
+
+    let mut shell = Command::new("sh");
+    shell.args(["-c", command]);
+    // outside the terminal's foreground group, a command reading from it is stopped, to be
+    // killed with Esc, rather than taking the keys meant for the interface
+    shell.process_group(0);
+    shell
+}
@@ -0,0 +1,88 @@
+use ratatui::{
+    Frame,
+    layout::{Constraint, Flex, Layout, Rect},
+    text::{Line, Text},
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+};
+use unicode_width::UnicodeWidthStr;
+
+/// Draws `text` in a bordered box centered over `area`, sized to fit it when possible.
+pub fn render_popup(frame: &mut Frame, area: Rect, title: &str, text: Text) {
+    let width = (text.width() as u16 + 4).max(title.width() as u16 + 4);
+    let height = text.height() as u16 + 2;
+    let popup_area = centered(area, width, height);
+
+    let paragraph = Paragraph::new(text)
+        .wrap(Wrap { trim: false })
+        .block(Block::new().borders(Borders::ALL).title(title));
+
+    frame.render_widget(Clear, popup_area);
+    frame.render_widget(paragraph, popup_area);
+}
+
+/// Scroll position of a popup too tall for the screen.
+#[derive(Debug, Default)]
+pub struct PopupScroll {
+    offset: u16,
+    // measured by the last draw, 0 when everything fits
+    max: u16,
+}
+
+impl PopupScroll {
+    pub fn is_scrollable(&self) -> bool {
+        self.max > 0
+    }
+
+    pub fn up(&mut self) {
+        self.offset = self.offset.saturating_sub(1);
+    }
+
+    pub fn down(&mut self) {
+        self.offset = (self.offset + 1).min(self.max);
+    }
+}
+
+/// Like [`render_popup`], but cut to the height of `area` and scrolled by `scroll` when `text`
+/// does not fit in it.
+pub fn render_scrollable_popup(
+    frame: &mut Frame,
+    area: Rect,
+    title: &str,
+    text: Text,
+    scroll: &mut PopupScroll,
+) {
+    let width = (text.width() as u16 + 4)
+        .max(title.width() as u16 + 4)
+        .min(area.width);
+    let paragraph = Paragraph::new(text).wrap(Wrap { trim: false });
+    // lines wrap when the popup is cut to the width of the screen
+    let text_height = paragraph.line_count(width.saturating_sub(2)) as u16;
+    let height = (text_height + 2).min(area.height);
+
+    scroll.max = text_height.saturating_sub(height.saturating_sub(2));
+    scroll.offset = scroll.offset.min(scroll.max);
+
+    let block = Block::new().borders(Borders::ALL).title(title);
+    let block = if scroll.is_scrollable() {
+        block.title_bottom(Line::from(" ↑/↓ to scroll ").right_aligned())
+    } else {
+        block
+    };
+    let popup_area = centered(area, width, height);
+
+    frame.render_widget(Clear, popup_area);
+    frame.render_widget(
+        paragraph.scroll((scroll.offset, 0)).block(block),
+        popup_area,
+    );
+}
+
+fn centered(area: Rect, width: u16, height: u16) -> Rect {
+    let [area] = Layout::horizontal([Constraint::Length(width)])
+        .flex(Flex::Center)
+        .areas(area);
+    let [area] = Layout::vertical([Constraint::Length(height)])
+        .flex(Flex::Center)
+        .areas(area);
+    area
+}
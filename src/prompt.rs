@@ -0,0 +1,77 @@
+use serde_json::Value;
+
+/// What the text typed in the status line is for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PromptKind {
+    Search,
+    Edit,
+    GoTo,
+    /// A shell command to pipe the selection through.
+    Pipe,
+    /// A command such as `w <file>`, see [`crate::command::Command`].
+    Command,
+    /// A value, or a `key = value` entry, to add next to the selection.
+    Add,
+    /// The new key of the selected object entry.
+    Rename,
+}
+
+/// Single line input shown in the status line.
+#[derive(Debug)]
+pub struct Prompt {
+    pub kind: PromptKind,
+    pub input: String,
+    /// Shown after the input when the last submission was rejected.
+    pub error: Option<String>,
+    /// Input submitted after a warning, accepted if submitted again unchanged.
+    pub warned: Option<String>,
+}
+
+impl Prompt {
+    pub fn new(kind: PromptKind, input: String) -> Self {
+        Self {
+            kind,
+            input,
+            error: None,
+            warned: None,
+        }
+    }
+
+    pub fn with_error(&self, line: String) -> String {
+        match &self.error {
+            Some(error) => format!("{line}  ({error})"),
+            None => line,
+        }
+    }
+}
+
+/// Splits an entry typed as `key = value`, the value being JSON. The key is taken as typed up
+/// to the `=`, or written as a JSON string when it holds one.
+pub fn parse_entry(input: &str) -> Result<(String, Value), String> {
+    let input = input.trim_start();
+
+    let (name, value) = if input.starts_with('"') {
+        let mut strings = serde_json::Deserializer::from_str(input).into_iter::<String>();
+        let name = match strings.next() {
+            Some(Ok(name)) => name,
+            _ => return Err("invalid key".to_string()),
+        };
+        let rest = input[strings.byte_offset()..].trim_start();
+        let value = rest
+            .strip_prefix('=')
+            .ok_or_else(|| "expected = after the key".to_string())?;
+        (name, value)
+    } else {
+        let (name, value) = input
+            .split_once('=')
+            .ok_or_else(|| "expected key = value".to_string())?;
+        (name.trim().to_string(), value)
+    };
+
+    if name.is_empty() {
+        return Err("expected a key before =, \"\" for an empty one".to_string());
+    }
+    let value = serde_json::from_str(value).map_err(|e| e.to_string())?;
+
+    Ok((name, value))
+}
@@ -0,0 +1,92 @@
+use ratatui::{
+    Frame,
+    layout::Rect,
+    style::Style,
+    widgets::{Block, Borders, List, ListState},
+};
+
+use json_tui::{node::Tree, path::format_path};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::search::Search;
+
+/// Side pane listing every match of the last search as `path: value`.
+#[derive(Debug, Default)]
+pub struct ResultsPane {
+    pub open: bool,
+    pub focused: bool,
+    state: ListState,
+}
+
+impl ResultsPane {
+    const VALUE_WIDTH: usize = 40;
+
+    /// Opens and focuses the pane, or closes it when it already has focus.
+    pub fn toggle(&mut self) {
+        if self.open && self.focused {
+            self.open = false;
+            self.focused = false;
+        } else {
+            self.open = true;
+            self.focused = true;
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.state.select(Some(0));
+        *self.state.offset_mut() = 0;
+    }
+
+    pub fn select_next(&mut self, len: usize) {
+        let i = self.state.selected().unwrap_or(0);
+        self.state.select(Some((i + 1).min(len.saturating_sub(1))));
+    }
+
+    pub fn select_previous(&mut self) {
+        let i = self.state.selected().unwrap_or(0);
+        self.state.select(Some(i.saturating_sub(1)));
+    }
+
+    pub fn selected(&self) -> Option<usize> {
+        self.state.selected()
+    }
+
+    pub fn render(&mut self, frame: &mut Frame, area: Rect, tree: &Tree, search: &Search) {
+        let items = search.matches().iter().map(|k| {
+            let value = truncate(&tree.summary(*k), Self::VALUE_WIDTH);
+            format!("{}: {value}", format_path(&tree.path_of(*k)))
+        });
+
+        let title = format!("{} matches", search.matches().len());
+        let block = Block::new().borders(Borders::ALL).title(title);
+        let block = if self.focused {
+            block
+        } else {
+            block.style(tree.theme().muted_style())
+        };
+
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(tree.theme().selection(Style::new()));
+
+        frame.render_stateful_widget(list, area, &mut self.state);
+    }
+}
+
+/// Cuts `s` to `max_width` columns, wide characters taking two.
+fn truncate(s: &str, max_width: usize) -> String {
+    if s.width() <= max_width {
+        return s.to_string();
+    }
+
+    let mut width = 0;
+    let mut ret: String = s
+        .chars()
+        .take_while(|c| {
+            width += c.width().unwrap_or(0);
+            width < max_width
+        })
+        .collect();
+    ret.push('…');
+    ret
+}
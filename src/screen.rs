@@ -0,0 +1,123 @@
+use std::{
+    fs::File,
+    io::{self, IsTerminal, Write},
+    panic::PanicHookInfo,
+};
+
+use crossterm::{
+    event::{DisableMouseCapture, EnableMouseCapture},
+    execute,
+    terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
+};
+use ratatui::{Terminal, backend::CrosstermBackend};
+
+/// The terminal the interface is drawn on.
+pub type Screen = Terminal<CrosstermBackend<Box<dyn Write + Send>>>;
+
+type PanicHook = Box<dyn Fn(&PanicHookInfo<'_>) + Send + Sync + 'static>;
+
+/// Where the interface writes to: stdout, or the controlling terminal when stdout is piped so that
+/// a picked value is all that goes down the pipe.
+pub fn output() -> io::Result<Box<dyn Write + Send>> {
+    if io::stdout().is_terminal() {
+        Ok(Box::new(io::stdout()))
+    } else {
+        Ok(Box::new(File::options().write(true).open("/dev/tty")?))
+    }
+}
+
+/// Switches to the alternate screen in raw mode with mouse capture, set back on panics before
+/// the report is printed.
+pub fn init() -> io::Result<Screen> {
+    check_terminal()?;
+
+    let report = std::panic::take_hook();
+    std::panic::set_hook(restoring_hook(
+        || {
+            let _ = restore();
+        },
+        report,
+    ));
+
+    enable_raw_mode()?;
+    let mut output = output()?;
+    execute!(output, EnterAlternateScreen, EnableMouseCapture)?;
+    Terminal::new(CrosstermBackend::new(output))
+}
+
+/// A panic hook calling `restore` before `report`, whose message would otherwise be printed on the
+/// alternate screen and lost with it.
+fn restoring_hook(restore: impl Fn() + Send + Sync + 'static, report: PanicHook) -> PanicHook {
+    Box::new(move |info| {
+        restore();
+        report(info);
+    })
+}
+
+/// Fails with an explanation when keys cannot be read. Piped input leaves them to the
+/// controlling terminal, which crossterm reads instead of stdin, but a job started without one,
+/// as by cron, has none.
+#[cfg(unix)]
+fn check_terminal() -> io::Result<()> {
+    if io::stdin().is_terminal() {
+        return Ok(());
+    }
+
+    File::options()
+        .read(true)
+        .write(true)
+        .open("/dev/tty")
+        .map(drop)
+        .map_err(|e| {
+            io::Error::new(
+                e.kind(),
+                format!(
+                    "no terminal to read keys from, stdin being piped and /dev/tty unavailable \
+                     ({e}); --output converts the input without the interface"
+                ),
+            )
+        })
+}
+
+/// The console stays available to crossterm on Windows whatever stdin is.
+#[cfg(not(unix))]
+fn check_terminal() -> io::Result<()> {
+    Ok(())
+}
+
+/// Sets the terminal back after [`restore`], for the interface to be drawn again in full.
+pub fn resume(terminal: &mut Screen) -> io::Result<()> {
+    enable_raw_mode()?;
+    execute!(output()?, EnterAlternateScreen, EnableMouseCapture)?;
+    terminal.clear()
+}
+
+pub fn restore() -> io::Result<()> {
+    disable_raw_mode()?;
+    execute!(output()?, DisableMouseCapture, LeaveAlternateScreen)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    #[test]
+    fn terminal_is_restored_before_the_panic_is_reported() {
+        let calls = Arc::new(Mutex::new(vec![]));
+        let (restored, reported) = (Arc::clone(&calls), Arc::clone(&calls));
+        std::panic::set_hook(restoring_hook(
+            move || restored.lock().unwrap().push("restore".to_string()),
+            Box::new(move |info| {
+                let message = info.payload().downcast_ref::<&str>().unwrap_or(&"");
+                reported.lock().unwrap().push(format!("report {message}"));
+            }),
+        ));
+
+        let result = std::panic::catch_unwind(|| panic!("boom"));
+        drop(std::panic::take_hook());
+        assert!(result.is_err());
+        assert_eq!(*calls.lock().unwrap(), ["restore", "report boom"]);
+    }
+}
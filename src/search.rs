@@ -0,0 +1,108 @@
+use slotmap::DefaultKey;
+
+use json_tui::node::Tree;
+
+#[derive(Debug, Default)]
+pub struct Search {
+    pub query: String,
+    pub rendered_only: bool,
+    by_key: bool,
+    matches: Vec<DefaultKey>,
+    current: usize,
+}
+
+impl Search {
+    pub fn run(&mut self, tree: &mut Tree) -> Option<DefaultKey> {
+        self.by_key = false;
+        self.matches = tree.find_matches(&self.query, !self.rendered_only);
+        self.current = 0;
+        tree.mark_matches(&self.matches);
+        self.matches.first().copied()
+    }
+
+    /// Searches for every entry sharing the selected node's key, starting from the selection.
+    pub fn run_current_key(&mut self, tree: &mut Tree) -> bool {
+        let current = tree.current_key();
+
+        let Some(name) = tree.key_name(current).map(str::to_string) else {
+            return false;
+        };
+
+        self.by_key = true;
+        self.matches = tree.find_key_matches(&name, !self.rendered_only);
+        self.query = name;
+        self.current = self.matches.iter().position(|k| *k == current).unwrap_or(0);
+        true
+    }
+
+    /// Drops the matches deleted from `tree`, keeping the current one when it is still there.
+    pub fn forget_deleted(&mut self, tree: &Tree) {
+        let current = self.matches.get(self.current).copied();
+        self.matches.retain(|k| tree.contains(*k));
+        self.current = current
+            .and_then(|c| self.matches.iter().position(|k| *k == c))
+            .unwrap_or(0);
+    }
+
+    pub fn matches(&self) -> &[DefaultKey] {
+        &self.matches
+    }
+
+    pub fn select(&mut self, index: usize) -> Option<DefaultKey> {
+        let key = self.matches.get(index).copied()?;
+        self.current = index;
+        Some(key)
+    }
+
+    pub fn next(&mut self) -> Option<DefaultKey> {
+        if self.matches.is_empty() {
+            return None;
+        }
+
+        self.current = (self.current + 1) % self.matches.len();
+        self.matches.get(self.current).copied()
+    }
+
+    pub fn previous(&mut self) -> Option<DefaultKey> {
+        if self.matches.is_empty() {
+            return None;
+        }
+
+        self.current = self
+            .current
+            .checked_sub(1)
+            .unwrap_or(self.matches.len() - 1);
+        self.matches.get(self.current).copied()
+    }
+
+    pub fn toggle_rendered_only(&mut self) {
+        self.rendered_only = !self.rendered_only;
+    }
+
+    pub fn scope(&self) -> &str {
+        if self.rendered_only {
+            "rendered only"
+        } else {
+            "all nodes"
+        }
+    }
+
+    pub fn prompt(&self, input: &str) -> String {
+        format!("/{input}  [Tab] search {}", self.scope())
+    }
+
+    pub fn status(&self) -> String {
+        let what = if self.by_key { "key " } else { "" };
+
+        if self.matches.is_empty() {
+            format!("no match for {what}\"{}\"", self.query)
+        } else {
+            format!(
+                "match {}/{} for {what}\"{}\"",
+                self.current + 1,
+                self.matches.len(),
+                self.query
+            )
+        }
+    }
+}
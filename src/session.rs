@@ -0,0 +1,89 @@
+use std::path::{Path, PathBuf};
+
+use color_eyre::{Result, eyre::eyre};
+use serde::{Deserialize, Serialize};
+
+use json_tui::{node::Tree, path::PathSegment};
+
+/// View state restored when reopening the same file with `--session`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Session {
+    collapsed: Vec<Vec<PathSegment>>,
+    cursor: Vec<PathSegment>,
+}
+
+impl Session {
+    pub fn capture(tree: &Tree) -> Self {
+        Self {
+            collapsed: tree.collapsed_paths(),
+            cursor: tree.current_path(),
+        }
+    }
+
+    /// Paths that no longer exist in the document are skipped.
+    pub fn apply(&self, tree: &mut Tree) {
+        for path in &self.collapsed {
+            if let Some(k) = tree.find_path(path) {
+                tree.set_collapsed(k, true);
+            }
+        }
+
+        if let Some(k) = tree.find_path(&self.cursor) {
+            tree.select(k);
+        }
+    }
+}
+
+/// Returns the state file for `input`, named after a hash of its canonical path. The hash is
+/// computed here rather than by `std`, whose hashers may change between Rust releases and would
+/// leave saved sessions behind.
+pub fn state_file(input: &str) -> Result<PathBuf> {
+    let cache_dir = match std::env::var_os("XDG_CACHE_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => std::env::var_os("HOME")
+            .map(|home| Path::new(&home).join(".cache"))
+            .ok_or_else(|| eyre!("cannot locate a cache directory for the session"))?,
+    };
+
+    let path = std::fs::canonicalize(input)?;
+    let hash = fnv1a(path.as_os_str().as_encoded_bytes());
+
+    Ok(cache_dir.join("json_tui").join(format!("{hash:016x}.json")))
+}
+
+/// The 64-bit FNV-1a hash of `bytes`.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    bytes.iter().fold(OFFSET_BASIS, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(PRIME)
+    })
+}
+
+pub fn load(file: &Path) -> Option<Session> {
+    let data = std::fs::read_to_string(file).ok()?;
+    serde_json::from_str(&data).ok()
+}
+
+pub fn save(file: &Path, session: &Session) -> Result<()> {
+    if let Some(dir) = file.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+
+    std::fs::write(file, serde_json::to_string(session)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn state_files_are_named_the_same_by_every_build() {
+        // published FNV-1a test vectors
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(fnv1a(b"foobar"), 0x8594_4171_f739_67e8);
+    }
+}
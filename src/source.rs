@@ -0,0 +1,339 @@
+use std::{collections::HashMap, ops::Range};
+
+use serde::{Deserialize, Deserializer};
+use serde_json::{Number, Value};
+use slotmap::DefaultKey;
+
+use crate::{
+    TreeError,
+    node::{NodeView, Tree},
+};
+
+/// The JSON values found back to back at the start of a text.
+#[derive(Debug)]
+pub struct Values {
+    pub values: Vec<Value>,
+    /// Where the text stops being readable after them, as with a comment or a truncated value,
+    /// the rest being left out.
+    pub trailing: Option<usize>,
+}
+
+/// A value read without serde_json's limit of 128 nesting levels, the stack growing on the heap
+/// as deeper levels are reached.
+struct Unbounded(Value);
+
+impl<'de> Deserialize<'de> for Unbounded {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Value::deserialize(serde_stacker::Deserializer::new(deserializer)).map(Unbounded)
+    }
+}
+
+/// Parses `text` as a single JSON value, however deeply nested, surrounded by nothing but
+/// whitespace:
+///
+/// ```
+/// use json_tui::source::read_value;
+///
+/// let deep = format!("{}{}", "[".repeat(1000), "]".repeat(1000));
+/// assert!(read_value(&deep).is_ok());
+/// assert!(read_value("[1] [2]").is_err());
+/// ```
+pub fn read_value(text: &str) -> Result<Value, serde_json::Error> {
+    let mut deserializer = serde_json::Deserializer::from_str(text);
+    deserializer.disable_recursion_limit();
+    let Unbounded(value) = Unbounded::deserialize(&mut deserializer)?;
+    deserializer.end()?;
+    Ok(value)
+}
+
+/// Reads the values of `text`, which can be followed by anything else, CRLF line ends and
+/// whitespace being stepped over. Fails when no value can be read. Values are read however
+/// deeply nested, as with [`read_value`].
+///
+/// ```
+/// use json_tui::source::read_values;
+/// use serde_json::json;
+///
+/// let read = read_values("{\"a\": 1}\r\n\r\n").unwrap();
+/// assert_eq!(read.values, [json!({"a": 1})]);
+/// assert_eq!(read.trailing, None);
+///
+/// let text = "{\"a\": 1}\r\n[2]\n// exported on Monday\n";
+/// let read = read_values(text).unwrap();
+/// assert_eq!(read.values, [json!({"a": 1}), json!([2])]);
+/// assert_eq!(&text[read.trailing.unwrap()..], "// exported on Monday\n");
+///
+/// assert!(read_values("{\"a\": }").is_err());
+/// assert!(read_values(" \r\n").is_err());
+/// ```
+pub fn read_values(text: &str) -> Result<Values, TreeError> {
+    let mut deserializer = serde_json::Deserializer::from_str(text);
+    deserializer.disable_recursion_limit();
+    let mut stream = deserializer.into_iter();
+    let mut values = vec![];
+
+    let trailing = loop {
+        match stream.next() {
+            Some(Ok(Unbounded(value))) => values.push(value),
+            Some(Err(e)) if values.is_empty() => return Err(e.into()),
+            Some(Err(_)) => {
+                let end = stream.byte_offset();
+                let rest = &text[end..];
+                break Some(end + rest.len() - rest.trim_start().len());
+            }
+            None if values.is_empty() => return Err(TreeError::Empty),
+            None => break None,
+        }
+    };
+
+    Ok(Values { values, trailing })
+}
+
+/// A value found in the source text, with the values it contains.
+struct Scanned {
+    span: Range<usize>,
+    object: bool,
+    /// Indices of the children in the scanned values, with their names in an object.
+    children: Vec<(Option<String>, usize)>,
+}
+
+/// Notes where each value of `tree` was read from in `text`, so that [`Tree::span`] can tell,
+/// and keeps the text of numbers that serde_json writes otherwise, such as `1e3`. Several
+/// documents given back to back, or the `lines` of NDJSON input, are matched with the items of
+/// the array they were read as. Comments, trailing commas and unquoted keys are stepped over as
+/// `--lenient` reads them.
+///
+/// ```
+/// use json_tui::{Tree, source};
+///
+/// let text = "{\"a\": [1E3, \"x\"]}";
+/// let mut tree = Tree::from_value(source::read_value(text).unwrap());
+/// source::record_spans(&mut tree, text, false);
+/// let x = tree.node_at_line(3).unwrap();
+/// assert_eq!(tree.span(x), Some(12..15));
+/// assert_eq!(tree.span(tree.root()), Some(0..text.len()));
+/// assert_eq!(tree.summary(tree.node_at_line(2).unwrap()), "1E3");
+/// ```
+pub fn record_spans(tree: &mut Tree, text: &str, lines: bool) {
+    let (scanned, documents) = scan(text);
+
+    let stack = match documents.as_slice() {
+        [document] if !lines => vec![(*document, tree.root())],
+        _ => {
+            // the documents are matched with the items of the root
+            tree.build(tree.root());
+            match tree.view(tree.root()) {
+                NodeView::Array(items) => documents
+                    .iter()
+                    .copied()
+                    .zip(items.iter().copied())
+                    .collect(),
+                _ => vec![],
+            }
+        }
+    };
+
+    record(tree, text, 0, &scanned, stack);
+    tree.keep_source(text);
+}
+
+/// Records the spans of the children of `key`, which was read from `span` of `text`, as
+/// [`record_spans`] does when the tree was read. Containers built after that go through this.
+pub(crate) fn record_spans_within(
+    tree: &mut Tree,
+    key: DefaultKey,
+    text: &str,
+    span: Range<usize>,
+) {
+    let (scanned, documents) = scan(&text[span.clone()]);
+    if let [document] = documents.as_slice() {
+        record(
+            tree,
+            &text[span.clone()],
+            span.start,
+            &scanned,
+            vec![(*document, key)],
+        );
+    }
+}
+
+/// Records the spans of the `scanned` values of `text` paired with nodes on `stack`, and of
+/// their descendants, `offset` being where `text` starts in the text the tree was read from.
+fn record(
+    tree: &mut Tree,
+    text: &str,
+    offset: usize,
+    scanned: &[Scanned],
+    mut stack: Vec<(usize, DefaultKey)>,
+) {
+    while let Some((index, key)) = stack.pop() {
+        let found = &scanned[index];
+        tree.set_span(key, found.span.start + offset..found.span.end + offset);
+
+        // left unbuilt, a container would write its numbers as serde_json does
+        if tree.is_unbuilt(key) && has_rewritten_number(scanned, index, text) {
+            tree.build(key);
+        }
+
+        let token = &text[found.span.clone()];
+        if let NodeView::Terminal(Value::Number(n)) = tree.view(key)
+            && token != n.as_str()
+            && token.parse::<Number>().is_ok_and(|read| read == *n)
+        {
+            tree.set_written(key, token);
+        }
+
+        match tree.view(key) {
+            NodeView::Array(items) if !found.object => {
+                stack.extend(
+                    found
+                        .children
+                        .iter()
+                        .map(|(_, child)| *child)
+                        .zip(items.iter().copied()),
+                );
+            }
+            NodeView::Object(entries) if found.object => {
+                let keys: HashMap<&str, _> = entries
+                    .iter()
+                    .map(|(name, k)| (name.as_str(), *k))
+                    .collect();
+                // a repeated name holds its last value, as when parsed
+                let matched: HashMap<_, _> = found
+                    .children
+                    .iter()
+                    .filter_map(|(name, child)| Some((*keys.get(name.as_deref()?)?, *child)))
+                    .collect();
+                stack.extend(matched.into_iter().map(|(key, child)| (child, key)));
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Whether a number among the scanned value `index` and its descendants is written otherwise
+/// than serde_json writes it, as `1e3` is.
+fn has_rewritten_number(scanned: &[Scanned], index: usize, text: &str) -> bool {
+    let mut stack = vec![index];
+
+    while let Some(index) = stack.pop() {
+        let found = &scanned[index];
+        let token = &text[found.span.clone()];
+        if token.parse::<Number>().is_ok_and(|n| n.as_str() != token) {
+            return true;
+        }
+        stack.extend(found.children.iter().map(|(_, child)| *child));
+    }
+
+    false
+}
+
+/// Finds the values of `text` and where they start and end, without checking that they are
+/// valid. Returns them along with the indices of the top-level ones.
+fn scan(text: &str) -> (Vec<Scanned>, Vec<usize>) {
+    let mut scanned: Vec<Scanned> = vec![];
+    let mut documents = vec![];
+    // containers not closed yet, and the name read for the next entry of an object
+    let mut open: Vec<usize> = vec![];
+    let mut name: Option<String> = None;
+    let mut pos = 0;
+
+    loop {
+        pos = skip_blank(text, pos);
+        let Some(c) = text[pos..].chars().next() else {
+            break;
+        };
+
+        match c {
+            ',' | ':' => pos += 1,
+            '}' | ']' => {
+                pos += 1;
+                if let Some(index) = open.pop() {
+                    scanned[index].span.end = pos;
+                }
+            }
+            _ => {
+                let in_object = open.last().is_some_and(|&index| scanned[index].object);
+                if in_object && name.is_none() {
+                    let end = if c == '"' {
+                        string_end(text, pos)
+                    } else {
+                        token_end(text, pos)
+                    };
+                    let key = &text[pos..end];
+                    name = Some(serde_json::from_str(key).unwrap_or_else(|_| key.to_string()));
+                    pos = end.max(pos + c.len_utf8());
+                    continue;
+                }
+
+                let index = scanned.len();
+                let end = match c {
+                    // until closed, if ever
+                    '{' | '[' => text.len(),
+                    '"' => string_end(text, pos),
+                    _ => token_end(text, pos).max(pos + c.len_utf8()),
+                };
+                scanned.push(Scanned {
+                    span: pos..end,
+                    object: c == '{',
+                    children: vec![],
+                });
+
+                match open.last() {
+                    Some(&parent) => scanned[parent].children.push((name.take(), index)),
+                    None => documents.push(index),
+                }
+                if matches!(c, '{' | '[') {
+                    open.push(index);
+                    pos += 1;
+                } else {
+                    pos = end;
+                }
+            }
+        }
+    }
+
+    (scanned, documents)
+}
+
+/// Skips whitespace and comments from `pos`.
+fn skip_blank(text: &str, mut pos: usize) -> usize {
+    loop {
+        let rest = &text[pos..];
+        let trimmed = rest.trim_start();
+        pos += rest.len() - trimmed.len();
+
+        if trimmed.starts_with("//") {
+            pos += trimmed.find('\n').unwrap_or(trimmed.len());
+        } else if let Some(comment) = trimmed.strip_prefix("/*") {
+            pos += comment.find("*/").map_or(trimmed.len(), |i| i + 4);
+        } else {
+            return pos;
+        }
+    }
+}
+
+/// End of the string starting at `pos`, past its closing quote.
+fn string_end(text: &str, pos: usize) -> usize {
+    let bytes = text.as_bytes();
+    let mut i = pos + 1;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'"' => return i + 1,
+            _ => i += 1,
+        }
+    }
+
+    text.len()
+}
+
+/// End of the number, literal or unquoted key starting at `pos`.
+fn token_end(text: &str, pos: usize) -> usize {
+    text[pos..]
+        .find(|c: char| {
+            c.is_whitespace() || matches!(c, ',' | ':' | '[' | ']' | '{' | '}' | '"' | '/')
+        })
+        .map_or(text.len(), |i| pos + i)
+}
@@ -0,0 +1,601 @@
+use std::{ops::Range, path::PathBuf, sync::Arc};
+
+use ratatui::{
+    Frame,
+    layout::{Margin, Rect},
+    style::Style,
+    text::{Line, Span, Text},
+    widgets::{Block, Paragraph, Scrollbar, ScrollbarOrientation, ScrollbarState, Wrap},
+};
+
+use json_tui::node::Tree;
+
+use crate::{results::ResultsPane, search::Search, wrap};
+
+/// An open document, with its own selection, search and scroll position.
+#[derive(Debug)]
+pub struct Tab {
+    pub title: String,
+    pub tree: Tree,
+    /// The input as it was read, before parsing.
+    raw: String,
+    /// The file the document was read from, `None` for stdin.
+    pub path: Option<PathBuf>,
+    /// Why the input could not be parsed, drawn in place of the tree, which is left empty.
+    pub error: Option<String>,
+    raw_view: RawView,
+    pub session_file: Option<PathBuf>,
+    pub search: Search,
+    pub results: ResultsPane,
+    /// Set after a jump so the next draw brings the selection into view, which starts with the
+    /// cursor restored by a session.
+    pub follow: bool,
+    scroll_x: u16,
+    scroll_y: u16,
+    // measured by the last draw
+    scroll_x_max: u16,
+    scroll_y_max: u16,
+    current_row: usize,
+    up_clamp: usize,
+    bot_clamp: usize,
+    // where the last draw put the text, and the screen row each line of it starts at
+    area: Rect,
+    line_rows: Vec<usize>,
+    // the text and wrapping width the rows were measured for, held so the text is not freed
+    // and another put in its place while compared by address, and the width of its longest line
+    measured: Option<(Arc<Text<'static>>, u16)>,
+    longest_line: usize,
+}
+
+/// Scroll position of the raw input, shown instead of the tree when open.
+#[derive(Debug, Default)]
+struct RawView {
+    open: bool,
+    scroll_x: u16,
+    scroll_y: u16,
+    scroll_x_max: u16,
+    scroll_y_max: u16,
+}
+
+impl Tab {
+    const PAN_STEP: u16 = 4;
+    const WHEEL_STEP: u16 = 3;
+
+    pub fn new(
+        title: String,
+        tree: Tree,
+        raw: String,
+        path: Option<PathBuf>,
+        session_file: Option<PathBuf>,
+    ) -> Self {
+        Self {
+            title,
+            tree,
+            raw,
+            path,
+            error: None,
+            raw_view: RawView::default(),
+            session_file,
+            search: Search::default(),
+            results: ResultsPane::default(),
+            follow: true,
+            scroll_x: 0,
+            scroll_y: 0,
+            scroll_x_max: 0,
+            scroll_y_max: 0,
+            current_row: 0,
+            up_clamp: 0,
+            bot_clamp: 0,
+            area: Rect::default(),
+            line_rows: vec![],
+            measured: None,
+            longest_line: 0,
+        }
+    }
+
+    /// Adds a line read once the input was loaded to the raw input.
+    pub fn append_raw(&mut self, line: &str) {
+        self.raw.push_str(line);
+        self.raw.push('\n');
+    }
+
+    /// Draws the tree inside `block`, scrolled to keep the selection in view, or the raw input
+    /// when it is shown.
+    pub fn render(&mut self, frame: &mut Frame, area: Rect, wrap: bool, block: Block) {
+        if self.raw_view.open {
+            self.render_raw(frame, area, wrap, block);
+            return;
+        }
+        if let Some(error) = &self.error {
+            let mut text = Text::raw(error.as_str());
+            text.push_line(Line::default());
+            text.push_line(Line::styled(
+                "R shows the input",
+                self.tree.theme().muted_style(),
+            ));
+            frame.render_widget(Paragraph::new(text).block(block), area);
+            return;
+        }
+
+        let width = area.width.saturating_sub(2);
+        // previews of collapsed containers are cut to the width the tree is drawn in
+        self.tree.set_view_width(width as usize);
+
+        let current_line = self.tree.find_current_line();
+        let text = self.tree.to_text();
+
+        // long lines wrap, so the scroll position is counted in screen rows, measured again
+        // only once the text or the width it wraps to changes
+        let wrap_width = if wrap { width } else { 0 };
+        let measured = self.measured.as_ref();
+        if !measured.is_some_and(|(old, w)| Arc::ptr_eq(old, &text) && *w == wrap_width) {
+            self.line_rows = wrap::line_rows(&text, wrap_width);
+            self.longest_line = text.lines.iter().map(Line::width).max().unwrap_or(0);
+            self.measured = Some((Arc::clone(&text), wrap_width));
+        }
+        let rows = std::mem::take(&mut self.line_rows);
+        self.current_row = rows[current_line.min(rows.len() - 1)];
+        let number_of_rows = rows[rows.len() - 1];
+
+        let total_height = (area.height as usize).saturating_sub(2); // account for borders
+        self.scroll_y_max = number_of_rows.saturating_sub(total_height) as u16;
+
+        if self.follow {
+            self.scroll_y = scroll_to_line(self.scroll_y, self.current_row, total_height);
+            self.follow = false;
+        }
+
+        if self.scroll_y > self.scroll_y_max {
+            self.scroll_y = self.scroll_y_max;
+        }
+
+        self.scroll_x_max = if wrap {
+            0
+        } else {
+            self.longest_line.saturating_sub(width as usize) as u16
+        };
+        self.scroll_x = self.scroll_x.min(self.scroll_x_max);
+
+        (self.up_clamp, self.bot_clamp) = {
+            let first_third = total_height / 3;
+            let second_third = first_third * 2;
+            let scroll_y = self.scroll_y as usize;
+            (first_third + scroll_y, second_third + scroll_y)
+        };
+
+        // only the lines in view are copied out of the shared text, to be styled and drawn
+        let scroll_y = self.scroll_y as usize;
+        let line_starts = &rows[..text.lines.len()];
+        let first = line_starts.partition_point(|&row| row <= scroll_y) - 1;
+        let last = line_starts.partition_point(|&row| row < scroll_y + total_height);
+        let mut shown = Text::from(text.lines[first..last.max(first + 1)].to_vec());
+        self.draw_selection(&mut shown, first);
+
+        // rows of the selected lines, filled edge to edge once the text is drawn
+        let selected_rows: Vec<_> = shown
+            .lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| line.style != Style::default())
+            .flat_map(|(i, line)| {
+                (rows[first + i]..rows[first + i + 1]).map(|row| (row, line.style))
+            })
+            .collect();
+
+        let block = edge_indicators(block, self.scroll_x, self.scroll_x_max);
+        let inner = block.inner(area);
+        self.area = inner;
+        let paragraph = Paragraph::new(shown)
+            .scroll(((scroll_y - rows[first]) as u16, self.scroll_x))
+            .block(block);
+        self.line_rows = rows;
+        let paragraph = if wrap {
+            paragraph.wrap(Wrap { trim: false })
+        } else {
+            paragraph
+        };
+
+        frame.render_widget(paragraph, area);
+        scrollbar(frame, area, self.scroll_y, self.scroll_y_max);
+
+        for (row, style) in selected_rows {
+            let Some(y) = row.checked_sub(self.scroll_y as usize) else {
+                continue;
+            };
+            if y < inner.height as usize {
+                let row = Rect::new(inner.x, inner.y + y as u16, inner.width, 1);
+                frame.buffer_mut().set_style(row, style);
+            }
+        }
+
+        // bookmarks are marked on the left border, leaving the text where it is
+        if inner.x > area.x {
+            for line in self.tree.bookmarked_lines() {
+                let Some(y) = self.line_rows[line].checked_sub(self.scroll_y as usize) else {
+                    continue;
+                };
+                if y < inner.height as usize {
+                    frame.buffer_mut()[(inner.x - 1, inner.y + y as u16)]
+                        .set_symbol("●")
+                        .set_style(self.tree.theme().key_style());
+                }
+            }
+        }
+    }
+
+    /// Styles the selection and the guide of the selected container over `text`, the lines of
+    /// the tree's text from `offset` on, which do not depend on them.
+    fn draw_selection(&self, text: &mut Text, offset: usize) {
+        let theme = self.tree.theme();
+        let end = offset + text.lines.len();
+        let shown = |(first, last): (usize, usize)| {
+            first.clamp(offset, end) - offset..last.clamp(offset, end) - offset
+        };
+
+        if let Some((column, lines)) = self.tree.active_guide() {
+            let lines = shown(lines);
+            for line in &mut text.lines[lines] {
+                restyle_guide(line, column, theme.punctuation_style());
+            }
+        }
+
+        let lines = shown(self.tree.selected_line_range());
+        for line in &mut text.lines[lines] {
+            for span in &mut line.spans {
+                span.style = theme.selection(span.style);
+            }
+            line.style = theme.selection(Style::default());
+        }
+    }
+
+    fn render_raw(&mut self, frame: &mut Frame, area: Rect, wrap: bool, block: Block) {
+        let raw = &mut self.raw_view;
+        let text = Text::raw(self.raw.as_str());
+        let width = area.width.saturating_sub(2);
+        let rows = wrap::line_rows(&text, if wrap { width } else { 0 });
+        let total_height = (area.height as usize).saturating_sub(2);
+
+        raw.scroll_y_max = rows[rows.len() - 1].saturating_sub(total_height) as u16;
+        raw.scroll_y = raw.scroll_y.min(raw.scroll_y_max);
+
+        let longest_line = text.lines.iter().map(Line::width).max();
+        raw.scroll_x_max = match longest_line {
+            Some(longest) if !wrap => longest.saturating_sub(width as usize) as u16,
+            _ => 0,
+        };
+        raw.scroll_x = raw.scroll_x.min(raw.scroll_x_max);
+
+        let block = edge_indicators(block, raw.scroll_x, raw.scroll_x_max);
+        self.area = block.inner(area);
+        let paragraph = Paragraph::new(text)
+            .scroll((raw.scroll_y, raw.scroll_x))
+            .block(block);
+        let paragraph = if wrap {
+            paragraph.wrap(Wrap { trim: false })
+        } else {
+            paragraph
+        };
+
+        frame.render_widget(paragraph, area);
+        scrollbar(frame, area, raw.scroll_y, raw.scroll_y_max);
+    }
+
+    /// Where the selected value was read from: its line and column in the input, counted from
+    /// 1, and its byte range.
+    pub fn source_position(&self) -> Option<(usize, usize, Range<usize>)> {
+        let span = self.tree.span(self.tree.current_key())?;
+        let before = self.raw.get(..span.start)?;
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        let line = before.matches('\n').count() + 1;
+        let column = before[line_start..].chars().count() + 1;
+        Some((line, column, span))
+    }
+
+    pub fn is_raw(&self) -> bool {
+        self.raw_view.open
+    }
+
+    pub fn toggle_raw(&mut self) {
+        self.raw_view.open = !self.raw_view.open;
+    }
+
+    pub fn scroll_raw_up(&mut self) {
+        self.raw_view.scroll_y = self.raw_view.scroll_y.saturating_sub(1);
+    }
+
+    pub fn scroll_raw_down(&mut self) {
+        self.raw_view.scroll_y = (self.raw_view.scroll_y + 1).min(self.raw_view.scroll_y_max);
+    }
+
+    pub fn move_up(&mut self) {
+        self.tree.next_node_up();
+        // the lines of the containers collapsed on the way are gone
+        self.follow |= self.tree.is_focus_mode();
+        self.current_row = self.selected_row();
+
+        if self.current_row < self.up_clamp {
+            let diff = self.up_clamp.saturating_sub(self.current_row) as u16;
+
+            self.scroll_y = self.scroll_y.saturating_sub(diff);
+        }
+    }
+
+    pub fn move_down(&mut self) {
+        self.tree.next_node_down();
+        self.follow |= self.tree.is_focus_mode();
+        self.current_row = self.selected_row();
+
+        if self.current_row > self.bot_clamp {
+            let diff = self.current_row.saturating_sub(self.bot_clamp) as u16;
+            self.scroll_y += diff;
+
+            if self.scroll_y > self.scroll_y_max {
+                self.scroll_y = self.scroll_y_max;
+            }
+        }
+    }
+
+    /// The screen row the selected line starts at in the last draw, which moving the selection
+    /// does not redraw.
+    fn selected_row(&self) -> usize {
+        let line = self.tree.find_current_line();
+        match self.line_rows.get(line) {
+            Some(&row) => row,
+            None => self.current_row,
+        }
+    }
+
+    /// Scrolls the selection to the middle of the view, as far as the text goes.
+    pub fn center(&mut self) {
+        let half = self.area.height as usize / 2;
+        self.scroll_y = (self.current_row.saturating_sub(half) as u16).min(self.scroll_y_max);
+    }
+
+    // panning only matters when long lines are not wrapped
+    pub fn pan_left(&mut self) {
+        let scroll_x = if self.raw_view.open {
+            &mut self.raw_view.scroll_x
+        } else {
+            &mut self.scroll_x
+        };
+        *scroll_x = scroll_x.saturating_sub(Self::PAN_STEP);
+    }
+
+    pub fn pan_right(&mut self) {
+        let (scroll_x, max) = if self.raw_view.open {
+            (&mut self.raw_view.scroll_x, self.raw_view.scroll_x_max)
+        } else {
+            (&mut self.scroll_x, self.scroll_x_max)
+        };
+        *scroll_x = (*scroll_x + Self::PAN_STEP).min(max);
+    }
+
+    /// Whether the screen cell at `column`, `row` is inside the text of the last draw.
+    pub fn contains(&self, column: u16, row: u16) -> bool {
+        self.area.contains((column, row).into())
+    }
+
+    pub fn wheel_up(&mut self) {
+        let scroll_y = if self.raw_view.open {
+            &mut self.raw_view.scroll_y
+        } else {
+            &mut self.scroll_y
+        };
+        *scroll_y = scroll_y.saturating_sub(Self::WHEEL_STEP);
+    }
+
+    pub fn wheel_down(&mut self) {
+        let (scroll_y, max) = if self.raw_view.open {
+            (&mut self.raw_view.scroll_y, self.raw_view.scroll_y_max)
+        } else {
+            (&mut self.scroll_y, self.scroll_y_max)
+        };
+        *scroll_y = (*scroll_y + Self::WHEEL_STEP).min(max);
+    }
+
+    /// Selects the node drawn at the screen cell `column`, `row`. Its visibility is toggled
+    /// when the click lands on a collapsed container's `{…}`/`[…]` marker, or on the node's first
+    /// line when `double` is set.
+    pub fn click(&mut self, column: u16, row: u16, double: bool) {
+        if self.raw_view.open || !self.contains(column, row) {
+            return;
+        }
+
+        let row = (row - self.area.y + self.scroll_y) as usize;
+        // the last line starting at or before the row
+        let line = self
+            .line_rows
+            .partition_point(|&r| r <= row)
+            .saturating_sub(1);
+        let Some(key) = self.tree.node_at_line(line) else {
+            return;
+        };
+        self.tree.select(key);
+
+        let first_line = line == self.tree.find_current_line();
+        // columns can only be told apart on lines that are not wrapped
+        let unwrapped = self.line_rows[line + 1] - self.line_rows[line] == 1;
+        let on_marker = first_line && unwrapped && {
+            let column = (column - self.area.x + self.scroll_x) as usize;
+            let text = self.tree.to_text();
+            marker_columns(&text.lines[line]).is_some_and(|columns| columns.contains(&column))
+        };
+
+        if on_marker || double && first_line {
+            self.tree.toggle_current_node_visibility();
+        }
+    }
+
+    pub fn reset_pan(&mut self) {
+        self.scroll_x = 0;
+        self.raw_view.scroll_x = 0;
+    }
+}
+
+/// Marks the bottom border with arrows on the sides where lines extend past the view.
+fn edge_indicators(block: Block, scroll_x: u16, scroll_x_max: u16) -> Block {
+    let block = if scroll_x > 0 {
+        block.title_bottom(Line::from("◀").left_aligned())
+    } else {
+        block
+    };
+
+    if scroll_x < scroll_x_max {
+        block.title_bottom(Line::from("▶").right_aligned())
+    } else {
+        block
+    }
+}
+
+/// Draws the scroll position over the right border of `area`, when the text does not fit in it.
+fn scrollbar(frame: &mut Frame, area: Rect, scroll_y: u16, scroll_y_max: u16) {
+    if scroll_y_max == 0 {
+        return;
+    }
+
+    let total_height = area.height.saturating_sub(2) as usize;
+    let mut state = ScrollbarState::new(scroll_y_max as usize + 1)
+        .position(scroll_y as usize)
+        .viewport_content_length(total_height);
+    let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
+        .begin_symbol(None)
+        .end_symbol(None)
+        .track_symbol(None)
+        .thumb_symbol("┃");
+
+    frame.render_stateful_widget(scrollbar, area.inner(Margin::new(0, 1)), &mut state);
+}
+
+/// Draws the indent guide at `column` of `line` with `style`, splitting it out of the span
+/// holding the whole indentation.
+fn restyle_guide(line: &mut Line, column: usize, style: Style) {
+    let mut start = 0;
+
+    for (i, span) in line.spans.iter().enumerate() {
+        let width = span.width();
+        if column >= start + width {
+            start += width;
+            continue;
+        }
+
+        // guides and the spaces between them take one column each
+        let Some((at, '│')) = span.content.char_indices().nth(column - start) else {
+            return;
+        };
+        let (before, after) = (&span.content[..at], &span.content[at + '│'.len_utf8()..]);
+        let pieces: Vec<_> = [
+            Span::styled(before.to_string(), span.style),
+            Span::styled("│", style),
+            Span::styled(after.to_string(), span.style),
+        ]
+        .into_iter()
+        .filter(|piece| !piece.content.is_empty())
+        .collect();
+        line.spans.splice(i..=i, pieces);
+        return;
+    }
+}
+
+/// Columns of the `{…}` or `[…]` marker standing for a collapsed container on `line`, drawn as its
+/// brackets around contents starting with an ellipsis.
+fn marker_columns(line: &Line) -> Option<Range<usize>> {
+    let mut column = 0;
+
+    for spans in line.spans.windows(3) {
+        let [open, contents, close] = spans else {
+            unreachable!("windows of 3 spans");
+        };
+        let brackets = (open.content.as_ref(), close.content.as_ref());
+        if matches!(brackets, ("{", "}") | ("[", "]")) && contents.content.starts_with('…') {
+            return Some(column..column + open.width() + contents.width() + close.width());
+        }
+        column += open.width();
+    }
+
+    None
+}
+
+/// Returns a scroll offset showing the row `line`, centering it if it is currently off screen.
+fn scroll_to_line(scroll_y: u16, line: usize, total_height: usize) -> u16 {
+    let top = scroll_y as usize;
+
+    if line >= top && line < top + total_height {
+        scroll_y
+    } else {
+        line.saturating_sub(total_height / 2) as u16
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::{Terminal, backend::TestBackend};
+
+    use super::*;
+
+    #[test]
+    fn selection_stays_in_view_over_wrapped_lines() {
+        let items: Vec<String> = (0..20)
+            .map(|i| format!(r#"{{"id": {i}, "text": "{}"}}"#, "word ".repeat(12)))
+            .collect();
+        let tree = Tree::try_from_str(&format!("[{}]", items.join(", "))).unwrap();
+        let mut tab = Tab::new("test".to_string(), tree, String::new(), None, None);
+        let mut terminal = Terminal::new(TestBackend::new(30, 12)).unwrap();
+        let mut draw = |tab: &mut Tab| {
+            terminal
+                .draw(|frame| tab.render(frame, frame.area(), true, Block::bordered()))
+                .unwrap();
+            terminal.backend().buffer().clone()
+        };
+
+        draw(&mut tab);
+        for _ in 0..80 {
+            tab.move_down();
+            let buffer = draw(&mut tab);
+
+            let row = tab.current_row as isize - tab.scroll_y as isize;
+            assert!(
+                (0..tab.area.height as isize).contains(&row),
+                "row {row} is out of view"
+            );
+            // the row drawn as selected shows the selected node
+            let y = tab.area.y + row as u16;
+            let shown: String = (tab.area.x..tab.area.right())
+                .map(|x| buffer[(x, y)].symbol())
+                .collect();
+            let line = tab.tree.to_text().lines[tab.tree.find_current_line()].to_string();
+            assert!(
+                line.starts_with(shown.trim_end()),
+                "{shown:?} is not {line:?}"
+            );
+        }
+        assert!(tab.scroll_y > 0);
+    }
+
+    #[test]
+    fn rows_are_measured_again_once_the_text_or_width_changes() {
+        let long = "x".repeat(60);
+        let tree = Tree::try_from_str(&format!(r#"{{"a": ["{long}"], "b": 1}}"#)).unwrap();
+        let mut tab = Tab::new("test".to_string(), tree, String::new(), None, None);
+        let mut terminal = Terminal::new(TestBackend::new(22, 12)).unwrap();
+        let mut draw = |tab: &mut Tab, wrap: bool| {
+            terminal
+                .draw(|frame| tab.render(frame, frame.area(), wrap, Block::bordered()))
+                .unwrap();
+        };
+        let measure = |tab: &Tab, width| wrap::line_rows(&tab.tree.to_text(), width);
+
+        draw(&mut tab, true);
+        assert_eq!(tab.line_rows, measure(&tab, 20));
+
+        // the array collapses to a single line
+        tab.move_down();
+        tab.tree.toggle_current_node_visibility();
+        draw(&mut tab, true);
+        assert_eq!(tab.line_rows, measure(&tab, 20));
+
+        tab.tree.toggle_current_node_visibility();
+        draw(&mut tab, false);
+        assert_eq!(tab.line_rows, measure(&tab, 0));
+        assert_eq!(tab.longest_line, long.len() + 6);
+    }
+}
@@ -0,0 +1,178 @@
+use ratatui::style::{Color, Modifier, Style};
+use serde_json::Value;
+
+use crate::{diff::DiffStatus, node::is_float};
+
+#[derive(Debug, Clone)]
+pub struct Theme {
+    pub key: Color,
+    pub string: Color,
+    pub number: Color,
+    /// Numbers written with a fraction or an exponent.
+    pub float: Color,
+    pub boolean: Color,
+    pub null: Color,
+    pub punctuation: Color,
+    /// When unset, selected spans keep their own foreground colour.
+    pub selection_fg: Option<Color>,
+    pub selection_bg: Color,
+    /// Shows the selection in reverse video instead of with `selection_bg`.
+    pub selection_reverse: bool,
+    pub match_bg: Color,
+    pub added: Color,
+    pub removed: Color,
+    pub changed: Color,
+    /// Drops every colour, relying on text attributes only.
+    pub monochrome: bool,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
+
+impl Theme {
+    pub const PRESETS: [&str; 2] = ["dark", "light"];
+
+    pub fn dark() -> Self {
+        Self {
+            key: Color::Blue,
+            string: Color::Green,
+            number: Color::Cyan,
+            float: Color::LightCyan,
+            boolean: Color::Yellow,
+            null: Color::DarkGray,
+            punctuation: Color::Gray,
+            selection_fg: None,
+            selection_bg: Color::Indexed(238),
+            selection_reverse: false,
+            match_bg: Color::Indexed(58),
+            added: Color::LightGreen,
+            removed: Color::LightRed,
+            changed: Color::LightYellow,
+            monochrome: false,
+        }
+    }
+
+    pub fn light() -> Self {
+        Self {
+            key: Color::Blue,
+            string: Color::Green,
+            number: Color::Magenta,
+            float: Color::Indexed(97),
+            boolean: Color::Red,
+            null: Color::Gray,
+            punctuation: Color::DarkGray,
+            selection_fg: None,
+            selection_bg: Color::Indexed(252),
+            selection_reverse: false,
+            match_bg: Color::Indexed(229),
+            added: Color::Green,
+            removed: Color::Red,
+            changed: Color::Indexed(130),
+            monochrome: false,
+        }
+    }
+
+    pub fn monochrome() -> Self {
+        Self {
+            monochrome: true,
+            ..Self::dark()
+        }
+    }
+
+    /// Returns the built-in theme called `name`, one of [`Theme::PRESETS`].
+    pub fn preset(name: &str) -> Option<Self> {
+        match name {
+            "dark" => Some(Self::dark()),
+            "light" => Some(Self::light()),
+            _ => None,
+        }
+    }
+
+    fn fg(&self, color: Color) -> Style {
+        if self.monochrome {
+            Style::new()
+        } else {
+            Style::new().fg(color)
+        }
+    }
+
+    pub fn key_style(&self) -> Style {
+        self.fg(self.key)
+    }
+
+    pub fn punctuation_style(&self) -> Style {
+        self.fg(self.punctuation)
+    }
+
+    /// Style of secondary text such as the title, the status line or container sizes.
+    pub fn muted_style(&self) -> Style {
+        if self.monochrome {
+            Style::new().add_modifier(Modifier::DIM)
+        } else {
+            Style::new().fg(Color::DarkGray)
+        }
+    }
+
+    pub fn value_style(&self, v: &Value) -> Style {
+        if self.monochrome {
+            return match v {
+                Value::Null => Style::new().add_modifier(Modifier::ITALIC),
+                _ => Style::new(),
+            };
+        }
+
+        match v {
+            Value::String(_) => Style::new().fg(self.string),
+            Value::Number(n) if is_float(n) => Style::new().fg(self.float),
+            Value::Number(_) => Style::new().fg(self.number),
+            Value::Bool(_) => Style::new().fg(self.boolean),
+            // set apart from the string "null"
+            Value::Null => Style::new().fg(self.null).add_modifier(Modifier::ITALIC),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Patches the selection over `style`, keeping its foreground unless `selection_fg` is set.
+    pub fn selection(&self, style: Style) -> Style {
+        if self.monochrome || self.selection_reverse {
+            return style.add_modifier(Modifier::REVERSED);
+        }
+
+        let style = style.bg(self.selection_bg);
+        match self.selection_fg {
+            Some(fg) => style.fg(fg),
+            None => style,
+        }
+    }
+
+    pub fn search_match(&self, style: Style) -> Style {
+        if self.monochrome {
+            return style.add_modifier(Modifier::UNDERLINED);
+        }
+
+        style.bg(self.match_bg)
+    }
+
+    /// Colours a value by how it differs from the other side of a comparison, or gives it an
+    /// attribute in monochrome: bold when added, crossed out when removed, italic when changed.
+    pub fn diff(&self, style: Style, status: DiffStatus) -> Style {
+        if self.monochrome {
+            let modifier = match status {
+                DiffStatus::Added => Modifier::BOLD,
+                DiffStatus::Removed => Modifier::CROSSED_OUT,
+                DiffStatus::Changed => Modifier::ITALIC,
+            };
+            return style.add_modifier(modifier);
+        }
+
+        let color = match status {
+            DiffStatus::Added => self.added,
+            DiffStatus::Removed => self.removed,
+            DiffStatus::Changed => self.changed,
+        };
+        style.fg(color)
+    }
+}
@@ -0,0 +1,60 @@
+use serde_json::Value;
+
+// 2000-01-01 and 2100-01-01, bounding the numbers taken for timestamps without a hint from the key
+const PLAUSIBLE_SECONDS: std::ops::Range<f64> = 946_684_800.0..4_102_444_800.0;
+// numbers above this are read as milliseconds
+const MILLISECONDS_FROM: f64 = 100_000_000_000.0;
+
+/// Returns the UTC date of `v` when it looks like a unix timestamp, in seconds or milliseconds,
+/// e.g. `2024-01-02 03:04:05`. Any non-negative number stored under a key such as `created_at`,
+/// `start_time` or `timestamp` is taken as one, other numbers only within years 2000 to 2100.
+pub(crate) fn annotation(name: Option<&str>, v: &Value) -> Option<String> {
+    let n = v.as_number()?.as_f64()?;
+    let named = name.is_some_and(|name| {
+        let name = name.to_lowercase();
+        name.ends_with("_at") || name.ends_with("_time") || name == "timestamp"
+    });
+
+    let seconds = if n >= MILLISECONDS_FROM {
+        n / 1000.0
+    } else {
+        n
+    };
+
+    if !(PLAUSIBLE_SECONDS.contains(&seconds)
+        || named && (0.0..PLAUSIBLE_SECONDS.end).contains(&seconds))
+    {
+        return None;
+    }
+
+    Some(format_utc(seconds as i64))
+}
+
+fn format_utc(seconds: i64) -> String {
+    let days = seconds.div_euclid(86_400);
+    let time = seconds.rem_euclid(86_400);
+    let (year, month, day) = civil_from_days(days);
+
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02}",
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+/// Converts a number of days since 1970-01-01 to a proleptic Gregorian date, after
+/// <https://howardhinnant.github.io/date_algorithms.html#civil_from_days>.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    (year, month, day)
+}
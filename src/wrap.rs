@@ -0,0 +1,57 @@
+use ratatui::{
+    text::Text,
+    widgets::{Paragraph, Wrap},
+};
+
+/// Returns the screen row at which each line of `text` starts once wrapped to `width` columns,
+/// followed by the total number of rows. A `width` of 0 counts one row per line, as when wrapping
+/// is turned off.
+pub fn line_rows(text: &Text, width: u16) -> Vec<usize> {
+    let mut rows = Vec::with_capacity(text.lines.len() + 1);
+    let mut row = 0;
+
+    for line in &text.lines {
+        rows.push(row);
+
+        // only lines wider than the view need the wrapping to be measured
+        row += if width == 0 || line.width() <= width as usize {
+            1
+        } else {
+            Paragraph::new(line.clone())
+                .wrap(Wrap { trim: false })
+                .line_count(width)
+        };
+    }
+
+    rows.push(row);
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use json_tui::Tree;
+
+    use super::*;
+
+    #[test]
+    fn long_string_in_a_narrow_area() {
+        let long = "x".repeat(60);
+        let tree = Tree::try_from_str(&format!(r#"{{"a": "{long}", "b": 1}}"#)).unwrap();
+        let text = tree.to_text();
+
+        let rows = line_rows(&text, 20);
+        // the string moves to a row of its own, then takes four
+        assert_eq!(rows, [0, 1, 6, 7, 8]);
+        assert_eq!(line_rows(&text, 0), [0, 1, 2, 3, 4]);
+
+        // every row the string wraps over leads back to it, and the rows below to their nodes
+        let node_at_row = |row: usize| {
+            let line = rows.partition_point(|&r| r <= row) - 1;
+            tree.node_at_line(line)
+        };
+        let (a, b) = (tree.node_at_line(1), tree.node_at_line(2));
+        assert!((1..6).all(|row| node_at_row(row) == a));
+        assert_eq!(node_at_row(6), b);
+        assert_eq!(node_at_row(7), tree.node_at_line(0));
+    }
+}
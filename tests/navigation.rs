@@ -0,0 +1,128 @@
+//! Invariants of moving the selection up and down, checked over documents of every shape the
+//! tree has special cases for.
+
+use json_tui::{DefaultKey, Tree, path::parse_path};
+use serde_json::{Value, json};
+
+/// Documents with empty containers, scalar roots and deep nesting, as trees fully expanded and
+/// with some subtrees collapsed.
+fn trees() -> Vec<Tree> {
+    let mut deep = json!("bottom");
+    for depth in 0..50 {
+        deep = if depth % 2 == 0 {
+            json!([deep, depth])
+        } else {
+            json!({"next": deep, "depth": depth})
+        };
+    }
+
+    let documents: Vec<Value> = vec![
+        json!({}),
+        json!([]),
+        json!({"a": {}, "b": [], "c": [[]], "d": [{}]}),
+        json!(42),
+        json!("x"),
+        json!(null),
+        json!([1, "two", null, true, {"a": [3.5]}]),
+        deep,
+    ];
+    let mut trees: Vec<Tree> = documents.into_iter().map(Tree::from_value).collect();
+
+    let collapsed = [
+        (
+            json!({"a": [1, 2], "b": {"c": {"d": 3}}, "e": 4}),
+            vec!["a", "b.c"],
+        ),
+        (
+            json!([[1, [2, [3]]], {"x": {}}, [4]]),
+            vec!["[0][1]", "[2]"],
+        ),
+        (json!({"a": {"b": 1}}), vec![""]),
+    ];
+    for (value, paths) in collapsed {
+        let mut tree = Tree::from_value(value);
+        for path in paths {
+            let key = tree.find_path(&parse_path(path).unwrap()).unwrap();
+            tree.set_collapsed(key, true);
+        }
+        trees.push(tree);
+    }
+
+    trees
+}
+
+/// The nodes drawn, in document order, read off the rendered lines rather than by moving.
+fn shown_nodes(tree: &Tree) -> Vec<DefaultKey> {
+    let mut shown: Vec<DefaultKey> = vec![];
+    for line in 0..tree.to_text().lines.len() {
+        let key = tree.node_at_line(line).unwrap();
+        if !shown.contains(&key) {
+            shown.push(key);
+        }
+    }
+    shown
+}
+
+/// Walks down from the root to the last node shown, returning the nodes visited.
+fn walk_down(tree: &mut Tree) -> Vec<DefaultKey> {
+    while tree.next_node_up().is_some() {}
+
+    let mut visited = vec![tree.current_key()];
+    while let Some(key) = tree.next_node_down() {
+        assert!(tree.contains(tree.current_key()));
+        visited.push(key);
+    }
+    visited
+}
+
+#[test]
+fn walking_down_visits_every_shown_node() {
+    for mut tree in trees() {
+        let visited = walk_down(&mut tree);
+        assert_eq!(visited, shown_nodes(&tree));
+        assert_eq!(Some(visited[0]), tree.find_path(&[]));
+    }
+}
+
+#[test]
+fn walking_up_retraces_the_walk_down() {
+    for mut tree in trees() {
+        let down = walk_down(&mut tree);
+
+        let mut up = vec![tree.current_key()];
+        while let Some(key) = tree.next_node_up() {
+            assert!(tree.contains(tree.current_key()));
+            up.push(key);
+        }
+        up.reverse();
+        assert_eq!(up, down);
+    }
+}
+
+#[test]
+fn down_then_up_returns_to_the_same_node() {
+    for mut tree in trees() {
+        for key in walk_down(&mut tree) {
+            tree.select(key);
+            if tree.next_node_down().is_some() {
+                assert_eq!(tree.next_node_up(), Some(key));
+            }
+            assert_eq!(tree.current_key(), key);
+            assert!(tree.contains(key));
+        }
+    }
+}
+
+#[test]
+fn moving_past_either_end_stays_put() {
+    for mut tree in trees() {
+        let last = *walk_down(&mut tree).last().unwrap();
+        assert_eq!(tree.next_node_down(), None);
+        assert_eq!(tree.current_key(), last);
+
+        while tree.next_node_up().is_some() {}
+        assert_eq!(tree.next_node_up(), None);
+        assert_eq!(Some(tree.current_key()), tree.find_path(&[]));
+        assert_eq!(tree.find_current_line(), 0);
+    }
+}